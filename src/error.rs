@@ -1,9 +1,26 @@
+use std::collections::HashSet;
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ErrorKind {
     Syntax,
     Runtime,
     Parse,
     Type,
+    Warning, // non-fatal, reported by lints and never stops a run
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::Syntax => "Syntax",
+            ErrorKind::Runtime => "Runtime",
+            ErrorKind::Parse => "Parse",
+            ErrorKind::Type => "Type",
+            ErrorKind::Warning => "Warning",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -31,6 +48,26 @@ pub struct Error {
     pub help: Option<String>,
 }
 
+/// `[line 1:5] Parse error: message` (`Warning: message` for warnings)
+/// followed by the help hint when there is one
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.kind {
+            ErrorKind::Warning => self.kind.to_string(),
+            _ => format!("{} error", self.kind),
+        };
+        write!(
+            f,
+            "[line {}:{}] {}: {}",
+            self.position.line, self.position.column, label, self.message
+        )?;
+        if let Some(help) = &self.help {
+            write!(f, "\n  help: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
 impl Error {
     fn new(kind: ErrorKind, message: impl Into<String>, position: Position) -> Self {
         Self {
@@ -59,6 +96,13 @@ impl Error {
     pub fn type_error(message: impl Into<String>, position: Position) -> Self {
         Self::new(ErrorKind::Type, message, position)
     }
+
+    pub fn warning(message: impl Into<String>, position: Position) -> Self {
+        Self::new(ErrorKind::Warning, message, position)
+    }
+    pub fn is_warning(&self) -> bool {
+        self.kind == ErrorKind::Warning
+    }
     pub fn unexpected_token(expected: &str, found: &str, position: Position) -> Self {
         Self::syntax(
             format!("Expected '{}', found '{}'", expected, found),
@@ -73,6 +117,23 @@ impl Error {
     pub fn division_by_zero(position: Position) -> Self {
         Self::runtime("Division by zero", position).with_help("Ensure the denominator is not zero")
     }
+    /// `{"kind": "runtime", "message": ..., "line": 1, "column": 5, "offset": 4, "help": null}`,
+    /// for editors and other tools that read errors
+    pub fn to_json(&self) -> String {
+        let help = match &self.help {
+            Some(help) => json_string(help),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"kind\": {}, \"message\": {}, \"line\": {}, \"column\": {}, \"offset\": {}, \"help\": {}}}",
+            json_string(&self.kind.to_string().to_lowercase()),
+            json_string(&self.message),
+            self.position.line,
+            self.position.column,
+            self.position.offset,
+            help
+        )
+    }
     pub fn invalid_operand_types(op: &str, left: &str, right: &str, position: Position) -> Self {
         Self::runtime(
             format!("Invalid operand types for {}: {} and {}", op, left, right),
@@ -81,3 +142,160 @@ impl Error {
         .with_help(&format!("The {} operator requires compatible types", op))
     }
 }
+
+/// every error found in a source instead of only the first one, for tooling like editors.
+/// the kind of each error tells the phase it comes from (syntax from scanning, parse, runtime)
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    errors: Vec<Error>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, error: Error) {
+        self.errors.push(error);
+    }
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+    /// whether anything worse than a warning was found
+    pub fn has_errors(&self) -> bool {
+        self.errors.iter().any(|error| !error.is_warning())
+    }
+    /// the errors in the order they were found
+    pub fn iter(&self) -> std::slice::Iter<'_, Error> {
+        self.errors.iter()
+    }
+    /// the first error found, the one a run that stops early would report
+    pub fn first(&self) -> Option<&Error> {
+        self.errors.first()
+    }
+    /// the errors ordered by where they appear in the source
+    pub fn sorted_by_position(&self) -> Vec<&Error> {
+        let mut errors: Vec<&Error> = self.errors.iter().collect();
+        errors.sort_by_key(|error| (error.position.line, error.position.column));
+        errors
+    }
+    /// order the errors by position and drop repeats of the same message at the same position,
+    /// which recovery tends to produce. the first occurrence is kept
+    pub fn sort_and_dedup(&mut self) {
+        self.errors
+            .sort_by_key(|error| (error.position.line, error.position.column));
+        let mut seen = HashSet::new();
+        self.errors.retain(|error| {
+            seen.insert((
+                error.position.line,
+                error.position.column,
+                error.message.clone(),
+            ))
+        });
+    }
+}
+
+impl Diagnostics {
+    /// a JSON array with one object per error (see `Error::to_json`), sorted by position
+    pub fn to_json(&self) -> String {
+        let errors: Vec<String> = self
+            .sorted_by_position()
+            .into_iter()
+            .map(Error::to_json)
+            .collect();
+        format!("[{}]", errors.join(", "))
+    }
+}
+
+/// `s` as a quoted JSON string, escaping quotes, backslashes and control characters
+pub(crate) fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// one error per line, sorted by position
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.sorted_by_position().into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::runtime::test_support::lox;
+
+    #[test]
+    fn scan_and_parse_errors_are_collected_in_source_order() {
+        let (mut lox, _) = lox();
+        let diagnostics = lox.run_with_diagnostics("print (1;\n@");
+        let kinds: Vec<ErrorKind> = diagnostics
+            .sorted_by_position()
+            .iter()
+            .map(|error| error.kind.clone())
+            .collect();
+        assert_eq!(kinds, [ErrorKind::Parse, ErrorKind::Syntax]);
+        assert_eq!(diagnostics.iter().count(), 2);
+    }
+
+    #[test]
+    fn runtime_error_is_collected_after_the_program_ran() {
+        let (mut lox, output) = lox();
+        let diagnostics = lox.run_with_diagnostics("print 1; print -\"a\";");
+        assert_eq!(output.contents(), "1\n");
+        assert_eq!(diagnostics.iter().count(), 1);
+        assert_eq!(diagnostics.first().unwrap().kind, ErrorKind::Type);
+    }
+
+    #[test]
+    fn identical_errors_at_one_position_collapse() {
+        let error = |message: &str, column| Error::parse(message, Position::new(1, column, 0));
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(error("late", 9));
+        diagnostics.push(error("same", 3));
+        diagnostics.push(error("same", 3).with_help("kept only once"));
+        diagnostics.push(error("other", 3));
+        diagnostics.sort_and_dedup();
+
+        let messages: Vec<&str> = diagnostics.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["same", "other", "late"]);
+        assert_eq!(diagnostics.first().unwrap().help, None);
+    }
+
+    #[test]
+    fn runtime_error_json_keeps_its_position() {
+        let (mut lox, _) = lox();
+        let diagnostics = lox.run_with_diagnostics("print 1;\n  missing;");
+        assert_eq!(
+            diagnostics.to_json(),
+            "[{\"kind\": \"runtime\", \"message\": \"Undefined variable 'missing'\", \
+             \"line\": 2, \"column\": 3, \"offset\": 11, \
+             \"help\": \"Make sure the variable is declared before use\"}]"
+        );
+        let error = Error::runtime("say \"hi\"\n", Position::new(1, 2, 1)).with_help("tab\t");
+        assert!(error
+            .to_json()
+            .contains("\"message\": \"say \\\"hi\\\"\\n\""));
+        assert!(error.to_json().ends_with("\"help\": \"tab\\t\"}"));
+        assert!(Error::parse("x", Position::new(1, 1, 0))
+            .to_json()
+            .ends_with("\"help\": null}"));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::error::{Error, Position};
+use crate::parser::value::Value;
+use crate::token::Token;
+use std::collections::{HashMap, HashSet};
+
+/// storage for the variables bound in a scope
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    constants: HashSet<String>, // names bound with `const`, they can't be assigned again
+}
+
+/// the bindings of an environment at some point, see `Environment::snapshot`
+#[derive(Debug, Clone)]
+pub struct Snapshot(Environment);
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+        }
+    }
+
+    /// bind a name to a value, a redefinition replaces the previous value
+    pub fn define(&mut self, name: impl Into<String>, value: Value) {
+        self.values.insert(name.into(), value);
+    }
+
+    /// bind a name that can't be assigned or redeclared afterwards
+    pub fn define_constant(&mut self, name: impl Into<String>, value: Value) {
+        let name = name.into();
+        self.constants.insert(name.clone());
+        self.values.insert(name, value);
+    }
+
+    /// unbind a name, giving back its value if it had one
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.values.remove(name)
+    }
+
+    /// save every binding so that `restore` can undo whatever happens afterwards, e.g. to try
+    /// a line in a playground and roll back its side effects when it fails. values are never
+    /// shared between bindings, so a copy is all it takes
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.clone())
+    }
+
+    /// go back to the bindings saved in the snapshot, dropping everything defined since
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        *self = snapshot.0;
+    }
+
+    pub fn is_constant(&self, name: &str) -> bool {
+        self.constants.contains(name)
+    }
+
+    /// change the value of an existing variable, constants and undefined names are errors
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), Error> {
+        let position = Position::new(name.line, name.column, name.offset);
+        if self.is_constant(&name.lexeme) {
+            return Err(Error::runtime(
+                format!("Cannot assign to constant '{}'", name.lexeme),
+                position,
+            ));
+        }
+        match self.values.get_mut(&name.lexeme) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(Error::undefined_variable(&name.lexeme, position)),
+        }
+    }
+
+    /// look up the value bound to the given identifier token
+    pub fn get(&self, name: &Token) -> Result<Value, Error> {
+        match self.values.get(&name.lexeme) {
+            Some(value) => Ok(value.clone()),
+            None => Err(Error::undefined_variable(
+                &name.lexeme,
+                Position::new(name.line, name.column, name.offset),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::runtime::test_support::lox;
+
+    #[test]
+    fn restoring_a_snapshot_undoes_later_bindings() {
+        let (mut lox, output) = lox();
+        lox.run("var kept = 1;").unwrap();
+        let snapshot = lox.interpreter.globals.snapshot();
+        lox.run("var added = 2; const fixed = 3; kept = 4;")
+            .unwrap();
+
+        lox.interpreter.globals.restore(snapshot);
+        lox.run("print kept; const fixed = 5; print fixed;")
+            .unwrap();
+        assert_eq!(output.contents(), "1\n5\n");
+        let error = lox.run("added;").unwrap_err();
+        assert_eq!(error.message, "Undefined variable 'added'");
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// how many lines are kept when no other size is asked for
+pub const DEFAULT_MAX_LINES: usize = 1000;
+
+/// lines typed at the REPL, saved to a file (one per line) so they survive between sessions
+pub struct History {
+    path: PathBuf,
+    lines: Vec<String>,
+    max_lines: usize, // only the newest lines are kept, 0 keeps nothing
+}
+
+impl History {
+    /// `~/.lox_history`, `None` when the home directory is unknown
+    pub fn default_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+        Some(PathBuf::from(home).join(".lox_history"))
+    }
+
+    /// read the history saved at `path`, a missing or unreadable file is an empty history
+    pub fn load(path: impl Into<PathBuf>, max_lines: usize) -> Self {
+        let path = path.into();
+        let lines = fs::read_to_string(&path)
+            .map(|text| text.lines().map(String::from).collect())
+            .unwrap_or_default();
+        let mut history = History {
+            path,
+            lines,
+            max_lines,
+        };
+        history.trim();
+        history
+    }
+
+    /// the saved lines, oldest first
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// remember a line and append it to the file, blank lines are skipped
+    pub fn add(&mut self, line: &str) -> io::Result<()> {
+        let line = line.trim_end();
+        if line.trim().is_empty() || self.max_lines == 0 {
+            return Ok(());
+        }
+        self.lines.push(line.to_string());
+
+        if self.trim() {
+            // over the cap, rewrite the file with only the newest lines
+            let mut text = self.lines.join("\n");
+            text.push('\n');
+            fs::write(&self.path, text)
+        } else {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            writeln!(file, "{}", line)
+        }
+    }
+
+    /// drop the oldest lines past the cap, telling whether any were dropped
+    fn trim(&mut self) -> bool {
+        let excess = self.lines.len().saturating_sub(self.max_lines);
+        self.lines.drain(..excess);
+        excess > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_lines_read_back_in_order_up_to_the_cap() {
+        let path = std::env::temp_dir().join(format!("lox-history-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut history = History::load(&path, 3);
+        for line in ["print 1;\n", "  \n", "var x = 2;\n", "x\n"] {
+            history.add(line).unwrap();
+        }
+        assert_eq!(
+            History::load(&path, 3).lines(),
+            ["print 1;", "var x = 2;", "x"]
+        );
+
+        // past the cap the oldest line goes, in memory and in the file
+        history.add("x + 1").unwrap();
+        assert_eq!(history.lines(), ["var x = 2;", "x", "x + 1"]);
+        assert_eq!(History::load(&path, 3).lines(), history.lines());
+        assert_eq!(History::load(&path, 2).lines(), ["x", "x + 1"]);
+        fs::remove_file(&path).unwrap();
+    }
+}
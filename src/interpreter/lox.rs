@@ -1,70 +1,891 @@
 // Scanning Part one
-use crate::error::{Error, ErrorKind};
+use crate::error::{Diagnostics, Error, ErrorKind, Position};
+use crate::interpreter::history::{self, History};
+use crate::interpreter::runtime::Interpreter;
+use crate::lexer::scanner::Scanner;
+use crate::parser::ast::{self, Stmt};
+use crate::parser::expr::{Expr, Notation};
+use crate::parser::json;
+use crate::parser::lint;
+use crate::parser::parser::Parser;
+use crate::token::{Token, TokenKind};
+use std::collections::HashMap;
 use std::io;
-use std::io::Write;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+// exit codes borrowed from sysexits.h, as in Crafting Interpreters
+const EXIT_USAGE: i32 = 64; // the command was used incorrectly
+const EXIT_DATA_ERROR: i32 = 65; // the source failed to scan or parse
+const EXIT_SOFTWARE: i32 = 70; // the program failed while running
+
+const SUBCOMMANDS: &[&str] = &["tokenize", "parse", "run", "check", "ast", "hash"];
+
+// REPL meta-commands and what they do, as listed by `:help`
+const COMMANDS: &[(&str, &str)] = &[
+    (":help", "list the commands"),
+    (
+        ":ast [notation] <expr>",
+        "print the syntax tree of the expression without evaluating it",
+    ),
+    (
+        ":type <expr>",
+        "print the type of the value of the expression",
+    ),
+    (":reset", "forget every variable defined so far"),
+    (
+        ":history",
+        "list the lines saved in the history, oldest first",
+    ),
+];
 
 // Core interpreter functionality
-pub struct Lox {}
+pub struct Lox {
+    pub interpreter: Interpreter,
+    /// when set, `parse_with_diagnostics` also runs the lints, whose warnings never stop a run
+    pub lint: bool,
+    /// when set, `run` writes every scanned token to the output before running, to debug the lexer
+    pub dump_tokens: bool,
+    /// when set, the CLI reports errors as JSON (see `Diagnostics::to_json`) instead of text
+    pub json_errors: bool,
+    /// longest source in bytes that is scanned, `None` for no limit. guards a REPL exposed as a
+    /// service against huge inputs
+    pub max_source_size: Option<usize>,
+    /// when set, `run` writes how long scanning, parsing and evaluation took to stderr
+    pub time: bool,
+    /// when set, `tokenize` keeps comments as tokens for tooling, the parser still skips them
+    pub keep_comments: bool,
+    /// width of a tab stop when counting columns, so error carets line up with the editor
+    pub tab_width: usize,
+    /// when set, a tab indenting a line is a syntax error, for classes that only allow spaces
+    pub forbid_tabs: bool,
+    /// whether `?:` is part of the language, unset for a teaching subset that rejects it
+    pub ternary: bool,
+    /// the words the scanner reads as keywords, the english set unless replaced by a localized one
+    pub keywords: HashMap<String, TokenKind>,
+}
 
 impl Lox {
     pub fn new() -> Self {
-        Lox {}
+        Lox {
+            interpreter: Interpreter::new(),
+            dump_tokens: false,
+            lint: false,
+            json_errors: false,
+            max_source_size: None,
+            time: false,
+            keep_comments: false,
+            tab_width: 4,
+            forbid_tabs: false,
+            ternary: true,
+            keywords: Scanner::default_keywords(),
+        }
+    }
+
+    /// errors as the CLI reports them, one per line or a JSON array with `json_errors`
+    pub fn format_diagnostics(&self, diagnostics: &Diagnostics) -> String {
+        match self.json_errors {
+            true => diagnostics.to_json(),
+            false => diagnostics.to_string(),
+        }
+    }
+
+    /// reject a source over `max_source_size` before any work is done on it
+    fn check_source_size(&self, source: &str) -> Result<(), Error> {
+        match self.max_source_size {
+            Some(max) if source.len() > max => Err(Error::syntax(
+                "Source exceeds maximum size",
+                Position::new(1, 1, 0),
+            )
+            .with_help(format!(
+                "The source is {} bytes, the limit is {} bytes",
+                source.len(),
+                max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// a scanner for the source set up with the scanning options
+    fn scanner(&self, source: &str) -> Scanner {
+        Scanner::new(source.to_string(), Vec::new(), 1)
+            .keep_comments(self.keep_comments)
+            .tab_width(self.tab_width)
+            .forbid_tabs(self.forbid_tabs)
+            .with_keywords(self.keywords.clone())
+    }
+
+    /// a parser for the tokens set up with the parsing options
+    fn parser(&self, tokens: Vec<Token>) -> Parser {
+        Parser::new(tokens).ternary(self.ternary)
+    }
+
+    /// scan the source into tokens, the Eof token included
+    pub fn tokenize(&self, source: &str) -> Result<Vec<Token>, Error> {
+        self.check_source_size(source)?;
+        self.scanner(source).get_tokens()
+    }
+
+    /// scan and parse the source without evaluating it
+    pub fn parse(&self, source: &str) -> Result<Expr, Error> {
+        let tokens = self.tokenize(source)?;
+        self.parser(tokens).parse()
+    }
+
+    /// scan and parse the source as a list of statements
+    pub fn parse_program(&self, source: &str) -> Result<Vec<Stmt>, Error> {
+        let tokens = self.tokenize(source)?;
+        self.parser(tokens).parse_program()
     }
-    /// Core execution method
-    pub fn run(&mut self, source: &str) -> Result<(), String> {
+
+    /// run a REPL meta-command, a line starting with ':' such as `:type 1 + 2`
+    pub fn run_command(&mut self, line: &str) -> Result<(), Error> {
+        let line = line.trim();
+        let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+        match command {
+            ":help" => {
+                for (usage, description) in COMMANDS {
+                    self.interpreter
+                        .write_line(&format!("{:<24} {}", usage, description));
+                }
+            }
+            ":ast" => {
+                // an optional notation name comes first, lisp by default
+                let (name, rest) = argument
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((argument, ""));
+                let (notation, source) = match Notation::from_name(name) {
+                    Some(notation) => (Some(notation), rest),
+                    None if name == "tree" => (None, rest),
+                    None => (Some(Notation::Lisp), argument),
+                };
+                let expr = self.parse(source)?;
+                let text = match notation {
+                    Some(notation) => expr.print(notation),
+                    None => expr.print_tree(),
+                };
+                self.interpreter.write_line(&text);
+            }
+            ":type" => {
+                let expr = self.parse(argument)?;
+                self.interpreter.start_deadline();
+                let value = expr.evaluate_in(&mut self.interpreter)?;
+                self.interpreter.write_line(value.type_name());
+            }
+            ":reset" => self.interpreter.reset(),
+            _ => self.interpreter.write_line(&format!(
+                "Unknown command '{}', :help lists the commands",
+                command
+            )),
+        }
         Ok(())
     }
+
+    /// whether the source stops in the middle of a statement (an unclosed paren or string,
+    /// a missing ';'), so the REPL should keep reading instead of reporting an error
+    pub fn is_incomplete(&self, source: &str) -> bool {
+        let tokens = match self.tokenize(source) {
+            Ok(tokens) => tokens,
+            Err(error) => return error.message.starts_with("Unterminated"),
+        };
+        let eof = tokens[tokens.len() - 1].clone();
+        match self.parser(tokens).parse_program() {
+            Err(error) => error.position.offset == eof.offset && error.position.line == eof.line,
+            Ok(_) => false,
+        }
+    }
+
+    /// scan and parse the whole source, collecting every error instead of stopping at the first
+    pub fn parse_with_diagnostics(&self, source: &str) -> (Vec<Stmt>, Diagnostics) {
+        let mut diagnostics = Diagnostics::new();
+        if let Err(error) = self.check_source_size(source) {
+            diagnostics.push(error);
+            return (Vec::new(), diagnostics);
+        }
+        let tokens = self.scanner(source).scan_with_diagnostics(&mut diagnostics);
+        let program = self.parser(tokens).parse_with_diagnostics(&mut diagnostics);
+        if self.lint {
+            lint::lint(&program, &mut diagnostics);
+        }
+        diagnostics.sort_and_dedup();
+        (program, diagnostics)
+    }
+
+    /// like `run` but reports every problem, the program only runs when it scanned and parsed cleanly
+    pub fn run_with_diagnostics(&mut self, source: &str) -> Diagnostics {
+        let (program, mut diagnostics) = self.parse_with_diagnostics(source);
+        if !diagnostics.has_errors() {
+            if let Err(error) = self.execute(&program) {
+                diagnostics.push(error);
+            }
+        }
+        diagnostics
+    }
+
+    /// Core execution method, a trailing expression without ';' has its value printed
+    pub fn run(&mut self, source: &str) -> Result<(), Error> {
+        let started = Instant::now();
+        let tokens = self.tokenize(source)?;
+        let scanned = Instant::now();
+        if self.dump_tokens {
+            for token in &tokens {
+                self.interpreter.write_line(&token.to_debug_line(source));
+            }
+        }
+        let parsing = Instant::now(); // the token dump isn't part of any phase
+        let program = self.parser(tokens).parse_program()?;
+        let evaluating = Instant::now();
+        let result = self.execute(&program);
+
+        if self.time {
+            // reported even when evaluation failed, a slow script that errors is still worth timing
+            let scanning = scanned - started;
+            eprintln!(
+                "{}",
+                timing_report(scanning, evaluating - parsing, evaluating.elapsed())
+            );
+        }
+        result
+    }
+
+    /// run parsed statements, a trailing expression without ';' has its value printed
+    fn execute(&mut self, program: &[Stmt]) -> Result<(), Error> {
+        self.interpreter.start_deadline();
+        for statement in program {
+            match statement {
+                Stmt::Expression {
+                    expr,
+                    semicolon: None,
+                } => {
+                    let value = expr.evaluate_in(&mut self.interpreter)?;
+                    let text = self.interpreter.display(&value);
+                    self.interpreter.write_line(&text);
+                }
+                _ => statement.execute(&mut self.interpreter)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the lines `--time` writes, one per phase with its duration
+fn timing_report(scanning: Duration, parsing: Duration, evaluating: Duration) -> String {
+    format!(
+        "scan:  {:?}\nparse: {:?}\neval:  {:?}",
+        scanning, parsing, evaluating
+    )
+}
+
+/// exit code for the phase that produced the error: 65 for scanning/parsing, 70 for runtime
+fn exit_code(error: &Error) -> i32 {
+    match error.kind {
+        ErrorKind::Syntax | ErrorKind::Parse => EXIT_DATA_ERROR,
+        ErrorKind::Runtime | ErrorKind::Type => EXIT_SOFTWARE,
+        ErrorKind::Warning => 0, // never returned as an error, warnings don't stop a run
+    }
+}
+
+/// report the error and exit with the code of the phase that failed
+fn exit_with(lox: &Lox, error: Error) -> ! {
+    let code = exit_code(&error);
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.push(error);
+    eprintln!("{}", lox.format_diagnostics(&diagnostics));
+    std::process::exit(code);
+}
+
+/// read a whole program at once so constructs spanning several lines stay together,
+/// `-` reads it from stdin, e.g. `cat prog.lox | lox -`
+fn read_source(path: &str) -> io::Result<String> {
+    if path == "-" {
+        read_all(io::stdin())
+    } else {
+        std::fs::read_to_string(path)
+    }
+}
+
+/// everything the reader has as one string, stdin for `lox -`
+fn read_all(mut reader: impl Read) -> io::Result<String> {
+    let mut source = String::new();
+    reader.read_to_string(&mut source)?;
+    Ok(source)
+}
+
+/// `lox tokenize FILE`: print one token per line, in the same format as `--dump-tokens`
+fn tokenize_file(lox: &mut Lox, path: &str) -> io::Result<()> {
+    let source = read_source(path)?;
+    match lox.tokenize(&source) {
+        Ok(tokens) => {
+            for token in tokens {
+                lox.interpreter.write_line(&token.to_debug_line(&source));
+            }
+        }
+        Err(e) => exit_with(lox, e),
+    }
+    Ok(())
+}
+
+/// `lox parse FILE`: print the syntax tree in the notation chosen with `--notation`
+fn parse_file(lox: &mut Lox, path: &str, notation: Notation) -> io::Result<()> {
+    let source = read_source(path)?;
+    match lox.parse(&source) {
+        Ok(expr) => lox.interpreter.write_line(&expr.print(notation)),
+        Err(e) => exit_with(lox, e),
+    }
+    Ok(())
+}
+
+/// `lox run FILE`: run file which contains .lox source code, the process exits at the first error.
+/// with `--error-format json` every scan and parse error is reported at once, for editors
+fn run_file(lox: &mut Lox, path: &str) -> io::Result<()> {
+    let source = read_source(path)?;
+    if lox.json_errors {
+        let diagnostics = lox.run_with_diagnostics(&source);
+        if !diagnostics.is_empty() {
+            eprintln!("{}", lox.format_diagnostics(&diagnostics));
+        }
+        if let Some(error) = diagnostics.iter().find(|error| !error.is_warning()) {
+            std::process::exit(exit_code(error));
+        }
+        return Ok(());
+    }
+    if lox.lint {
+        let (_, diagnostics) = lox.parse_with_diagnostics(&source);
+        let mut warnings = Diagnostics::new();
+        for warning in diagnostics.iter().filter(|error| error.is_warning()) {
+            warnings.push(warning.clone());
+        }
+        if !warnings.is_empty() {
+            eprintln!("{}", lox.format_diagnostics(&warnings));
+        }
+    }
+    if let Err(e) = lox.run(&source) {
+        exit_with(lox, e);
+    }
+    Ok(())
+}
+
+/// `lox check FILE`: report every scan and parse problem (and lint warning with `--lint`)
+/// without running anything, the exit code is 65 when there was an error
+fn check_file(lox: &mut Lox, path: &str) -> io::Result<()> {
+    let source = read_source(path)?;
+    match check(lox, &source) {
+        0 => Ok(()),
+        code => std::process::exit(code),
+    }
+}
+
+/// report the problems `lox check` finds in the source and return the exit code for them
+fn check(lox: &Lox, source: &str) -> i32 {
+    let (_, diagnostics) = lox.parse_with_diagnostics(source);
+    if !diagnostics.is_empty() {
+        eprintln!("{}", lox.format_diagnostics(&diagnostics));
+    }
+    match diagnostics.has_errors() {
+        true => EXIT_DATA_ERROR,
+        false => 0,
+    }
+}
+
+/// `lox ast FILE`: print the syntax tree of the whole program as JSON, with `--max-depth` the
+/// nodes past it are cut short
+fn ast_file(lox: &mut Lox, path: &str, max_depth: Option<usize>) -> io::Result<()> {
+    let source = read_source(path)?;
+    match lox.parse_program(&source) {
+        Ok(program) => {
+            let json = match max_depth {
+                Some(max_depth) => json::program_to_json_to_depth(&program, max_depth),
+                None => json::program_to_json(&program),
+            };
+            lox.interpreter.write_line(&json);
+        }
+        Err(e) => exit_with(lox, e),
+    }
+    Ok(())
 }
 
-/// run file which contains .lox source code
-fn run_file<P: AsRef<Path>>(lox: &mut Lox, path: P) -> io::Result<()> {
-    let content = std::fs::read_to_string(path);
-    for line in content?.lines() {
-        if let Err(e) = lox.run(&line) {
-            // // dummy
-            //
-            // let error = Error::new(ErrorKind::Runtime, "Error in parsing", 1, 1);
-            // error.print(&e);
+/// `lox hash FILE`: print a hash of the program's structure, equal for sources that differ only
+/// in spacing and comments, e.g. to know whether a cached result is still good
+fn hash_file(lox: &mut Lox, path: &str) -> io::Result<()> {
+    let source = read_source(path)?;
+    match lox.parse_program(&source) {
+        Ok(program) => {
+            let hash = format!("{:016x}", ast::program_hash(&program));
+            lox.interpreter.write_line(&hash);
         }
+        Err(e) => exit_with(lox, e),
     }
     Ok(())
 }
-/// run a single prompt from the interactive shell
-fn run_prompt(lox: &mut Lox) -> io::Result<()> {
+
+/// the text of a REPL line ending in `\`, without it, when the user wants to keep typing
+fn continued_line(line: &str) -> Option<&str> {
+    line.trim_end().strip_suffix('\\')
+}
+
+/// run a single prompt from the interactive shell, errors are reported and the session goes on.
+/// every non-blank line is saved to the history when there is one, a line ending in `\` is
+/// always continued on the next one
+fn run_prompt(lox: &mut Lox, mut history: Option<History>) -> io::Result<()> {
     let stdin = io::stdin();
     let mut buffer = String::new();
 
     loop {
-        print!("> ");
+        // an unfinished statement keeps the buffer and asks for more with "... "
+        print!("{}", if buffer.is_empty() { "> " } else { "... " });
         std::io::stdout().flush()?;
 
-        buffer.clear();
-        stdin.read_line(&mut buffer)?;
-        if buffer.trim().is_empty() {
+        let mut line = String::new();
+        stdin.read_line(&mut line)?;
+        if line.trim().is_empty() && buffer.is_empty() {
             break;
         }
+        if let Some(history) = &mut history {
+            // losing the history is not worth interrupting the session
+            let _ = history.add(&line);
+        }
+        // the history belongs to the prompt rather than to Lox, so it is listed here
+        if buffer.is_empty() && line.trim() == ":history" {
+            let lines = history.as_ref().map(History::lines).unwrap_or_default();
+            for (number, entry) in lines.iter().enumerate() {
+                lox.interpreter
+                    .write_line(&format!("{:>5}  {}", number + 1, entry));
+            }
+            continue;
+        }
+        if buffer.is_empty() && line.trim_start().starts_with(':') {
+            if let Err(e) = lox.run_command(&line) {
+                eprintln!("{}", e);
+            }
+            continue;
+        }
+        if let Some(text) = continued_line(&line) {
+            // asked for explicitly, keep reading even when the buffer would already parse
+            buffer.push_str(text);
+            buffer.push('\n');
+            continue;
+        }
+        buffer.push_str(&line);
 
+        // a blank line gives up on continuing, so the error is shown
+        if !line.trim().is_empty() && lox.is_incomplete(&buffer) {
+            continue;
+        }
         if let Err(e) = lox.run(&buffer) {
-            // //dummy
-            // let error = Error::new(ErrorKind::Runtime, "Error in parsing", 1, 1);
-            // error.print(&e);
+            eprintln!("{}", e);
         }
+        buffer.clear();
     }
     Ok(())
 }
 
+/// print how to invoke the tool and exit with the usage error code
+fn usage() -> ! {
+    eprintln!("Usage: lox [path | -]");
+    eprintln!("       lox <tokenize | parse | run | check | ast | hash> <path | ->");
+    eprintln!("Options:");
+    eprintln!("  --notation <name>                 notation used by parse: lisp, lisp-compact, rpn or polish (default: lisp)");
+    eprintln!("  --max-depth <depth>               nodes printed in full by ast, deeper ones are cut short");
+    eprintln!("  --trace                           print every node and its value while running");
+    eprintln!("  --lint                            warn about suspicious code before running");
+    eprintln!("  --dump-tokens                     print every scanned token with its position before running");
+    eprintln!("  --keep-comments                   include comments in the tokens printed by tokenize and --dump-tokens");
+    eprintln!("  --error-format <text | json>      how errors are reported (default: text)");
+    eprintln!("  --precision <digits>              significant digits numbers are printed with");
+    eprintln!("  --ieee                            let arithmetic overflow to inf and nan instead of failing");
+    eprintln!(
+        "  --tab-width <columns>             width of a tab stop in reported columns (default: 4)"
+    );
+    eprintln!("  --forbid-tabs                     report tabs used to indent a line as errors");
+    eprintln!("  --no-ternary                      reject the ?: operator, for a teaching subset");
+    eprintln!("  --time                            print how long scanning, parsing and evaluation took to stderr");
+    eprintln!("  --history-size <lines>            REPL lines kept in ~/.lox_history, 0 disables it (default: 1000)");
+    std::process::exit(EXIT_USAGE);
+}
+
+/// remove the boolean flag `name` from the arguments and tell whether it was there
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|arg| arg == name) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// remove `name value` from the arguments and return the value, a missing value is a usage error
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == name)?;
+    args.remove(index);
+    if index >= args.len() {
+        usage();
+    }
+    Some(args.remove(index))
+}
+
 fn main() -> io::Result<()> {
     let mut lox = Lox::new();
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+
+    let notation = match take_option(&mut args, "--notation") {
+        Some(name) => Notation::from_name(&name).unwrap_or_else(|| {
+            eprintln!("Unknown notation '{}'", name);
+            usage()
+        }),
+        None => Notation::Lisp,
+    };
+    let max_depth = take_option(&mut args, "--max-depth").map(|depth| {
+        depth.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid depth '{}'", depth);
+            usage()
+        })
+    });
+    lox.interpreter.trace = take_flag(&mut args, "--trace");
+    lox.interpreter.ieee_numbers = take_flag(&mut args, "--ieee");
+    lox.dump_tokens = take_flag(&mut args, "--dump-tokens");
+    lox.keep_comments = take_flag(&mut args, "--keep-comments");
+    lox.lint = take_flag(&mut args, "--lint");
+    lox.time = take_flag(&mut args, "--time");
+    lox.forbid_tabs = take_flag(&mut args, "--forbid-tabs");
+    lox.ternary = !take_flag(&mut args, "--no-ternary");
+    lox.json_errors = match take_option(&mut args, "--error-format").as_deref() {
+        Some("json") => true,
+        Some("text") | None => false,
+        Some(format) => {
+            eprintln!("Unknown error format '{}'", format);
+            usage()
+        }
+    };
+    lox.interpreter.precision = match take_option(&mut args, "--precision") {
+        Some(digits) => match digits.parse() {
+            Ok(digits) if digits > 0 => Some(digits),
+            _ => {
+                eprintln!("Invalid precision '{}'", digits);
+                usage()
+            }
+        },
+        None => None,
+    };
+    if let Some(width) = take_option(&mut args, "--tab-width") {
+        lox.tab_width = match width.parse() {
+            Ok(width) if width > 0 => width,
+            _ => {
+                eprintln!("Invalid tab width '{}'", width);
+                usage()
+            }
+        };
+    }
+    let history_size = match take_option(&mut args, "--history-size") {
+        Some(size) => size.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid history size '{}'", size);
+            usage()
+        }),
+        None => history::DEFAULT_MAX_LINES,
+    };
 
-    match args.len() {
-        1 => run_prompt(&mut lox)?,
-        2 => run_file(&mut lox, &args[1])?,
-        _ => {
-            eprintln!("Usage: lox [path]");
-            std::process::exit(64);
+    match args.as_slice() {
+        [_] => {
+            let history = History::default_path().map(|path| History::load(path, history_size));
+            run_prompt(&mut lox, history)?
         }
+        [_, command, path] if command == "tokenize" => tokenize_file(&mut lox, path)?,
+        [_, command, path] if command == "parse" => parse_file(&mut lox, path, notation)?,
+        [_, command, path] if command == "run" => run_file(&mut lox, path)?,
+        [_, command, path] if command == "check" => check_file(&mut lox, path)?,
+        [_, command, path] if command == "ast" => ast_file(&mut lox, path, max_depth)?,
+        [_, command, path] if command == "hash" => hash_file(&mut lox, path)?,
+        [_, path] if !SUBCOMMANDS.contains(&path.as_str()) => run_file(&mut lox, path)?,
+        _ => usage(),
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::runtime::test_support;
+    use std::io::Cursor;
+
+    #[test]
+    fn exit_code_follows_the_failing_phase() {
+        let mut lox = Lox::new();
+        let parse_error = lox.run("(1 + 2").unwrap_err();
+        assert_eq!(exit_code(&parse_error), EXIT_DATA_ERROR);
+        let scan_error = lox.run("@").unwrap_err();
+        assert_eq!(exit_code(&scan_error), EXIT_DATA_ERROR);
+        let runtime_error = lox.run("undefined_name;").unwrap_err();
+        assert_eq!(exit_code(&runtime_error), EXIT_SOFTWARE);
+        assert_eq!((EXIT_DATA_ERROR, EXIT_SOFTWARE), (65, 70));
+    }
+
+    #[test]
+    fn stdin_program_runs_as_a_whole() {
+        let input = Cursor::new("var x = 1;\nprint x +\n  1;\n");
+        let source = read_all(input).unwrap();
+        let (mut lox, output) = test_support::lox();
+        lox.run(&source).unwrap();
+        assert_eq!(output.contents(), "2\n");
+    }
+
+    #[test]
+    fn file_runs_as_a_whole() {
+        let path = test_support::source_file("two-lines", "print 1 +\n  2;\n");
+        let (mut lox, output) = test_support::lox();
+        run_file(&mut lox, path.to_str().unwrap()).unwrap();
+        assert_eq!(output.contents(), "3\n");
+    }
+
+    #[test]
+    fn subcommands_print_tokens_tree_and_output() {
+        let path = test_support::source_file("subcommands", "1 + 2");
+        let path = path.to_str().unwrap();
+
+        let (mut lox, output) = test_support::lox();
+        tokenize_file(&mut lox, path).unwrap();
+        assert_eq!(
+            output.contents(),
+            "Number[1:1] \"1\"\nPlus[1:3] \"+\"\nNumber[1:5] \"2\"\nEof[1:6] \"\"\n"
+        );
+
+        let (mut lox, output) = test_support::lox();
+        parse_file(&mut lox, path, Notation::Lisp).unwrap();
+        assert_eq!(output.contents(), "(+ 1 2)\n");
+
+        let (mut lox, output) = test_support::lox();
+        run_file(&mut lox, path).unwrap();
+        assert_eq!(output.contents(), "3\n");
+    }
+
+    #[test]
+    fn sources_over_the_size_limit_are_rejected_unrun() {
+        let (mut lox, output) = test_support::lox();
+        lox.max_source_size = Some(9);
+        lox.run("print 1;").unwrap();
+        lox.run("print 12;").unwrap();
+        let error = lox.run("print 123;").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Syntax);
+        assert_eq!(error.message, "Source exceeds maximum size");
+        assert_eq!(output.contents(), "1\n12\n");
+        // every entry point checks it
+        assert!(lox.tokenize("print 123;").is_err());
+        let (_, diagnostics) = lox.parse_with_diagnostics("print 123;");
+        assert_eq!(diagnostics.iter().count(), 1);
+    }
+
+    #[test]
+    fn timing_report_has_a_line_per_phase() {
+        let report = timing_report(
+            Duration::from_micros(1500),
+            Duration::from_millis(2),
+            Duration::from_secs(3),
+        );
+        assert_eq!(report, "scan:  1.5ms\nparse: 2ms\neval:  3s");
+
+        // timing goes to stderr, the program output is unchanged
+        let (mut lox, output) = test_support::lox();
+        lox.time = true;
+        lox.run("print 1;").unwrap();
+        assert_eq!(output.contents(), "1\n");
+    }
+
+    #[test]
+    fn kept_comments_are_tokens_but_not_syntax() {
+        let mut lox = Lox::new();
+        let source = "print 1; // hi";
+        assert_eq!(lox.tokenize(source).unwrap().len(), 4);
+
+        lox.keep_comments = true;
+        let tokens = lox.tokenize(source).unwrap();
+        assert_eq!(tokens[3].kind, TokenKind::Comment);
+        assert_eq!(tokens[3].lexeme, "// hi");
+
+        let (mut lox, output) = test_support::lox();
+        lox.keep_comments = true;
+        lox.run(source).unwrap();
+        assert_eq!(output.contents(), "1\n");
+    }
+
+    #[test]
+    fn error_columns_follow_the_tab_width() {
+        let mut lox = Lox::new();
+        assert_eq!(lox.tokenize("\t@").unwrap_err().position.column, 5);
+        lox.tab_width = 8;
+        assert_eq!(lox.tokenize("\t@").unwrap_err().position.column, 9);
+    }
+
+    #[test]
+    fn keywords_can_be_localized() {
+        let (mut lox, output) = test_support::lox();
+        lox.keywords.remove("print");
+        lox.keywords.insert("stampa".into(), TokenKind::Print);
+        lox.keywords.insert("vero".into(), TokenKind::True);
+        lox.run("stampa vero;").unwrap();
+        assert_eq!(output.contents(), "true\n");
+        // the english word is an identifier now
+        assert_eq!(lox.run("print 1;").unwrap_err().kind, ErrorKind::Parse);
+    }
+
+    #[test]
+    fn unfinished_input_asks_for_another_line() {
+        let (mut lox, output) = test_support::lox();
+        let mut buffer = String::from("switch (1) {\n");
+        assert!(lox.is_incomplete(&buffer));
+        buffer.push_str("  case 1: print \"one\";\n");
+        assert!(lox.is_incomplete(&buffer));
+        buffer.push_str("}\n");
+        assert!(!lox.is_incomplete(&buffer));
+        lox.run(&buffer).unwrap();
+        assert_eq!(output.contents(), "one\n");
+
+        for unfinished in ["print (1 +", "{1: 2", "\"abc", "var x = 1"] {
+            assert!(lox.is_incomplete(unfinished), "{}", unfinished);
+        }
+        // an error before the end won't be fixed by reading more
+        for wrong in ["print );", "1 +;", ")"] {
+            assert!(!lox.is_incomplete(wrong), "{}", wrong);
+        }
+    }
+
+    #[test]
+    fn dumped_tokens_show_their_positions_before_the_output() {
+        let (mut lox, output) = test_support::lox();
+        lox.dump_tokens = true;
+        lox.run("var x = 1;\nprint x;").unwrap();
+        let expected = [
+            "Var[1:1] \"var\"",
+            "Identifier[1:5] \"x\"",
+            "Equal[1:7] \"=\"",
+            "Number[1:9] \"1\"",
+            "Semicolon[1:10] \";\"",
+            "Print[2:1] \"print\"",
+            "Identifier[2:7] \"x\"",
+            "Semicolon[2:8] \";\"",
+            "Eof[2:9] \"\"",
+            "1",
+        ];
+        assert_eq!(output.contents(), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn type_command_prints_the_type_name() {
+        let (mut lox, output) = test_support::lox();
+        lox.run_command(":type 1 + 2").unwrap();
+        lox.run_command(":type \"a\" + \"b\"").unwrap();
+        lox.run_command(":type {}").unwrap();
+        assert_eq!(output.contents(), "number\nstring\nmap\n");
+    }
+
+    #[test]
+    fn help_lists_every_command() {
+        let (mut lox, output) = test_support::lox();
+        lox.run_command(":help").unwrap();
+        let help = output.contents();
+        assert_eq!(help.lines().count(), COMMANDS.len());
+        for (usage, _) in COMMANDS {
+            assert!(help.contains(usage));
+        }
+    }
+
+    #[test]
+    fn ast_command_prints_without_evaluating() {
+        let (mut lox, output) = test_support::lox();
+        lox.run_command(":ast rpn 1 + 2").unwrap();
+        lox.run_command(":ast 1 + 2").unwrap();
+        lox.run_command(":ast polish undefined_name * 2").unwrap();
+        assert_eq!(output.contents(), "1 2 +\n(+ 1 2)\n* undefined_name 2\n");
+    }
+
+    #[test]
+    fn check_fails_only_on_errors_and_runs_nothing() {
+        let (mut lox, output) = test_support::lox();
+        assert_eq!(check(&lox, "print 1;"), 0);
+        assert_eq!(check(&lox, "print (1;"), EXIT_DATA_ERROR);
+        assert_eq!(check(&lox, "print 1; @"), EXIT_DATA_ERROR);
+
+        // warnings are reported but don't fail the check
+        lox.lint = true;
+        assert_eq!(check(&lox, "1 + 2;"), 0);
+        assert_eq!(output.contents(), "");
+    }
+
+    #[test]
+    fn trailing_backslash_continues_the_line() {
+        assert_eq!(continued_line("print 1 + \\\n"), Some("print 1 + "));
+        assert_eq!(continued_line("print 1; \\  \r\n"), Some("print 1; "));
+        assert_eq!(continued_line("print \"\\\\\";\n"), None);
+        assert_eq!(continued_line("print 1;\n"), None);
+
+        // joined the way the prompt does, even a line that is complete on its own waits for the
+        // next one, and both run as one program
+        let (mut lox, output) = test_support::lox();
+        let first = continued_line("var x = 1; \\\n").unwrap();
+        assert!(!lox.is_incomplete(first));
+        lox.run(&format!("{}\n{}", first, "print x + 1;")).unwrap();
+        assert_eq!(output.contents(), "2\n");
+    }
+
+    #[test]
+    fn ast_prints_the_program_as_json() {
+        let path = test_support::source_file("ast", "var a = 1;\nprint a + 2;");
+        let path = path.to_str().unwrap();
+
+        let (mut lox, output) = test_support::lox();
+        ast_file(&mut lox, path, None).unwrap();
+        let whole = output.contents();
+        assert!(whole.starts_with("{\n  \"statements\": [\n    {\n      \"type\": \"Var\""));
+        assert!(!whole.contains("truncated"));
+
+        let (mut lox, output) = test_support::lox();
+        ast_file(&mut lox, path, Some(1)).unwrap();
+        assert!(output.contents().contains("\"truncated\": true"));
+    }
+
+    #[test]
+    fn indenting_tabs_are_errors_only_when_forbidden() {
+        let source = "var x = 1;\n\tprint x;\n  print\tx;";
+        let (mut lox, output) = test_support::lox();
+        lox.run(source).unwrap();
+        assert_eq!(output.contents(), "1\n1\n");
+
+        lox.forbid_tabs = true;
+        let error = lox.run(source).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Syntax);
+        assert_eq!(error.message, "Tab used for indentation");
+        assert_eq!((error.position.line, error.position.column), (2, 1));
+        // a tab after the indentation is fine
+        lox.run("print\t2;").unwrap();
+        assert_eq!(output.contents(), "1\n1\n2\n");
+    }
+
+    #[test]
+    fn hash_ignores_spacing_and_comments() {
+        let hash = |name: &str, source: &str| {
+            let path = test_support::source_file(name, source);
+            let (mut lox, output) = test_support::lox();
+            hash_file(&mut lox, path.to_str().unwrap()).unwrap();
+            output.contents()
+        };
+        let tight = hash("hash-tight", "print 1+2;");
+        assert_eq!(tight.trim_end().len(), 16);
+        assert_eq!(hash("hash-spaced", "// sum\nprint 1 +\n  2;\n"), tight);
+        assert_ne!(hash("hash-other", "print 2+1;"), tight);
+    }
+
+    #[test]
+    fn ternary_can_be_left_out_of_the_language() {
+        let (mut lox, output) = test_support::lox();
+        lox.run("print true ? 1 : 2;").unwrap();
+        assert_eq!(output.contents(), "1\n");
+
+        lox.ternary = false;
+        let error = lox.run("print 1;\nprint true ? 1 : 2;").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.message, "Ternary operator is disabled in this mode");
+        assert_eq!((error.position.line, error.position.column), (2, 12));
+        // nothing ran, the whole program is parsed first
+        assert_eq!(output.contents(), "1\n");
+        assert!(lox.parse("a ? b : c").is_err());
+    }
+}
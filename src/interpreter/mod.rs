@@ -1 +1,5 @@
-pub mod lox;
\ No newline at end of file
+pub mod environment;
+pub mod history;
+pub mod lox;
+pub mod natives;
+pub mod runtime;
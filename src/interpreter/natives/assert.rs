@@ -0,0 +1,88 @@
+use crate::error::{Error, Position};
+use crate::interpreter::natives::{Arity, NativeFunction};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::value::Value;
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "assert",
+        arity: Arity::between(1, 2),
+        function: assert,
+    },
+    NativeFunction {
+        name: "assert_eq",
+        arity: Arity::exactly(2),
+        function: assert_eq,
+    },
+];
+
+/// `assert(condition)` or `assert(condition, message)`, fails at the call site when the
+/// condition is not truthy so `.lox` files can check themselves
+fn assert(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    if args[0].is_truthy() {
+        return Ok(Value::Nil);
+    }
+    let message = match args.get(1) {
+        Some(message) => format!("Assertion failed: {}", message),
+        None => "Assertion failed".to_string(),
+    };
+    Err(Error::runtime(message, position.clone()))
+}
+
+/// `assert_eq(a, b)`: fails when the values aren't equal under `==`, showing both. strings are
+/// quoted so `assert_eq(1, "1")` doesn't read as `1 != 1`
+fn assert_eq(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    if args[0] == args[1] {
+        return Ok(Value::Nil);
+    }
+    Err(Error::runtime(
+        format!("Assertion failed: {} != {}", args[0].repr(), args[1].repr()),
+        position.clone(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ErrorKind;
+    use crate::interpreter::runtime::test_support::run;
+
+    #[test]
+    fn passing_assert_does_nothing() {
+        assert_eq!(
+            run("assert(true); assert(1, \"one\"); print 1;").unwrap(),
+            "1\n"
+        );
+    }
+
+    #[test]
+    fn failing_assert_points_at_the_call() {
+        let error = run("print 1;\n  assert(false);").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "Assertion failed");
+        // calls are reported at their closing parenthesis
+        assert_eq!((error.position.line, error.position.column), (2, 15));
+    }
+
+    #[test]
+    fn failing_assert_shows_its_message() {
+        let error = run("assert(1 > 2, \"math is broken\");").unwrap_err();
+        assert_eq!(error.message, "Assertion failed: math is broken");
+    }
+
+    #[test]
+    fn assert_eq_passes_on_equal_values() {
+        let source =
+            "assert_eq(1 + 1, 2); assert_eq(\"a\", \"a\"); assert_eq({1: nil}, {1: nil}); print 1;";
+        assert_eq!(run(source).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn assert_eq_shows_both_values() {
+        let error = run("assert_eq(1 + 1, 3);").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "Assertion failed: 2 != 3");
+        // strings are quoted so "1" and 1 can be told apart
+        let error = run("assert_eq(\"1\", 1);").unwrap_err();
+        assert_eq!(error.message, "Assertion failed: \"1\" != 1");
+    }
+}
@@ -0,0 +1,31 @@
+use crate::error::{Error, Position};
+use crate::interpreter::natives::{Arity, NativeFunction};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::value::Value;
+
+pub const NATIVES: &[NativeFunction] = &[NativeFunction {
+    name: "len",
+    arity: Arity::exactly(1),
+    function: len,
+}];
+
+/// `len(x)`: characters of a string, bytes of a byte string, entries of a map or numbers of a
+/// range
+fn len(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let length = match &args[0] {
+        Value::String(s) => s.chars().count(),
+        Value::Bytes(bytes) => bytes.len(),
+        Value::Map(map) => map.len(),
+        Value::Range(start, end) => Value::range_len(*start, *end) as usize,
+        other => {
+            return Err(Error::type_error(
+                format!(
+                    "'len' expects a string, bytes, a map or a range, found {}",
+                    other.type_name()
+                ),
+                position.clone(),
+            ))
+        }
+    };
+    Ok(Value::Number(length as f64))
+}
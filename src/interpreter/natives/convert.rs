@@ -0,0 +1,35 @@
+use crate::error::{Error, Position};
+use crate::interpreter::natives::{Arity, NativeFunction};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::value::Value;
+
+pub const NATIVES: &[NativeFunction] = &[NativeFunction {
+    name: "bool",
+    arity: Arity::exactly(1),
+    function: bool,
+}];
+
+/// `bool(x)`: whether `x` counts as true in a condition, only `false` and `nil` don't.
+/// an explicit conversion, so it works the same under strict truthiness
+fn bool(_: &mut Interpreter, args: &[Value], _: &Position) -> Result<Value, Error> {
+    Ok(Value::Bool(args[0].is_truthy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::runtime::test_support::{lox, run};
+
+    #[test]
+    fn only_false_and_nil_convert_to_false() {
+        let output = run("print bool(0), bool(\"\"), bool({}), bool(nil), bool(false);");
+        assert_eq!(output.unwrap(), "true true true false false\n");
+    }
+
+    #[test]
+    fn bool_converts_under_strict_truthiness() {
+        let (mut lox, output) = lox();
+        lox.interpreter.strict_truthiness = true;
+        lox.run("print bool(nil), bool(1);").unwrap();
+        assert_eq!(output.contents(), "false true\n");
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error::{Error, Position};
+use crate::interpreter::natives::{Arity, NativeFunction};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::value::Value;
+use std::io;
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "read_line",
+        arity: Arity::exactly(0),
+        function: read_line,
+    },
+    NativeFunction {
+        name: "println",
+        arity: Arity::between(0, 1),
+        function: println,
+    },
+];
+
+/// read the next line from the interpreter input without its line ending, nil at end of input
+fn read_line(
+    interpreter: &mut Interpreter,
+    _: &[Value],
+    position: &Position,
+) -> Result<Value, Error> {
+    let mut line = String::new();
+    let read = match &mut interpreter.input {
+        Some(input) => input.read_line(&mut line),
+        None => io::stdin().read_line(&mut line),
+    };
+    match read {
+        Ok(0) => Ok(Value::Nil),
+        Ok(_) => {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(Value::String(line.into()))
+        }
+        Err(e) => Err(Error::runtime(
+            format!("Failed to read input: {}", e),
+            position.clone(),
+        )),
+    }
+}
+
+/// `println(value)` writes the value and a newline to the interpreter output and returns nil.
+/// it is the expression form of the `print` keyword, which is why it can't be called `print`
+fn println(interpreter: &mut Interpreter, args: &[Value], _: &Position) -> Result<Value, Error> {
+    match args.first() {
+        Some(value) => interpreter.write_line(&interpreter.display(value)),
+        None => interpreter.write_line(""),
+    }
+    Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::runtime::test_support::{lox, run};
+    use std::io::Cursor;
+
+    #[test]
+    fn read_line_reads_the_injected_input_in_order() {
+        let (mut lox, output) = lox();
+        lox.interpreter.input = Some(Box::new(Cursor::new("first\r\nsecond\n")));
+        lox.run("print read_line(); print read_line(); print read_line();")
+            .unwrap();
+        assert_eq!(output.contents(), "first\nsecond\nnil\n");
+    }
+
+    #[test]
+    fn println_writes_to_the_output_and_returns_nil() {
+        assert_eq!(run("print println(\"x\");").unwrap(), "x\nnil\n");
+        assert_eq!(run("println(); print 1;").unwrap(), "\n1\n");
+    }
+}
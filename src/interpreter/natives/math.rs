@@ -0,0 +1,120 @@
+use crate::error::{Error, Position};
+use crate::interpreter::natives::{number_argument, Arity, NativeFunction};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::value::Value;
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "sqrt",
+        arity: Arity::exactly(1),
+        function: sqrt,
+    },
+    NativeFunction {
+        name: "abs",
+        arity: Arity::exactly(1),
+        function: abs,
+    },
+    NativeFunction {
+        name: "floor",
+        arity: Arity::exactly(1),
+        function: floor,
+    },
+    NativeFunction {
+        name: "ceil",
+        arity: Arity::exactly(1),
+        function: ceil,
+    },
+    NativeFunction {
+        name: "round",
+        arity: Arity::exactly(1),
+        function: round,
+    },
+    NativeFunction {
+        name: "pow",
+        arity: Arity::exactly(2),
+        function: pow,
+    },
+    NativeFunction {
+        name: "min",
+        arity: Arity::exactly(2),
+        function: min,
+    },
+    NativeFunction {
+        name: "max",
+        arity: Arity::exactly(2),
+        function: max,
+    },
+];
+
+fn sqrt(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let n = number_argument("sqrt", &args[0], position)?;
+    Ok(Value::Number(n.sqrt()))
+}
+
+fn abs(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let n = number_argument("abs", &args[0], position)?;
+    Ok(Value::Number(n.abs()))
+}
+
+fn floor(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let n = number_argument("floor", &args[0], position)?;
+    Ok(Value::Number(n.floor()))
+}
+
+fn ceil(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let n = number_argument("ceil", &args[0], position)?;
+    Ok(Value::Number(n.ceil()))
+}
+
+fn round(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let n = number_argument("round", &args[0], position)?;
+    Ok(Value::Number(n.round()))
+}
+
+fn pow(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let base = number_argument("pow", &args[0], position)?;
+    let exponent = number_argument("pow", &args[1], position)?;
+    Ok(Value::Number(base.powf(exponent)))
+}
+
+fn min(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let a = number_argument("min", &args[0], position)?;
+    let b = number_argument("min", &args[1], position)?;
+    Ok(Value::Number(a.min(b)))
+}
+
+fn max(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let a = number_argument("max", &args[0], position)?;
+    let b = number_argument("max", &args[1], position)?;
+    Ok(Value::Number(a.max(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ErrorKind;
+    use crate::interpreter::runtime::test_support::run;
+
+    #[test]
+    fn math_natives() {
+        assert_eq!(run("sqrt(9)").unwrap(), "3\n");
+        assert_eq!(run("abs(-3)").unwrap(), "3\n");
+        assert_eq!(run("pow(2, 10)").unwrap(), "1024\n");
+        assert_eq!(
+            run("print floor(2.5), ceil(2.5), round(2.5);").unwrap(),
+            "2 3 3\n"
+        );
+        assert_eq!(run("print min(1, 2), max(1, 2);").unwrap(), "1 2\n");
+    }
+
+    #[test]
+    fn non_number_argument_is_a_type_error() {
+        let error = run("sqrt(\"x\")").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(error.message, "'sqrt' expects a number, found string");
+    }
+
+    #[test]
+    fn wrong_argument_count_is_an_error() {
+        assert!(run("pow(2)").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+pub mod assert;
+pub mod collections;
+pub mod convert;
+pub mod io;
+pub mod math;
+pub mod random;
+pub mod strings;
+pub mod time;
+
+use crate::error::{Error, Position};
+use crate::interpreter::environment::Environment;
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::value::Value;
+use std::fmt;
+
+/// signature shared by every native, it receives the interpreter (for natives that keep state),
+/// the evaluated arguments and the call position
+pub type NativeFn = fn(&mut Interpreter, &[Value], &Position) -> Result<Value, Error>;
+
+/// function implemented in rust and exposed to lox code as a global
+#[derive(Debug, Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: Arity,
+    pub function: NativeFn,
+}
+
+/// how many arguments a native accepts, the trailing ones past `min` are optional
+#[derive(Debug, Clone, Copy)]
+pub struct Arity {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl Arity {
+    pub const fn exactly(count: usize) -> Self {
+        Arity {
+            min: count,
+            max: count,
+        }
+    }
+
+    pub const fn between(min: usize, max: usize) -> Self {
+        Arity { min, max }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        self.min <= count && count <= self.max
+    }
+}
+
+/// `2` for a fixed arity, `1 to 2` when some arguments are optional
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{} to {}", self.min, self.max)
+        }
+    }
+}
+
+/// install every native function into the global environment
+pub fn install(globals: &mut Environment) {
+    let natives = math::NATIVES
+        .iter()
+        .chain(random::NATIVES)
+        .chain(io::NATIVES)
+        .chain(assert::NATIVES)
+        .chain(collections::NATIVES)
+        .chain(convert::NATIVES)
+        .chain(time::NATIVES);
+    for native in natives {
+        globals.define(native.name, Value::Native(native.clone()));
+    }
+}
+
+/// extract a number argument or fail with a type error naming the native
+pub fn number_argument(name: &str, value: &Value, position: &Position) -> Result<f64, Error> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        _ => Err(Error::type_error(
+            format!("'{}' expects a number, found {}", name, value.type_name()),
+            position.clone(),
+        )),
+    }
+}
+
+/// extract a string argument or fail with a type error naming the native
+pub fn string_argument<'a>(
+    name: &str,
+    value: &'a Value,
+    position: &Position,
+) -> Result<&'a str, Error> {
+    match value {
+        Value::String(s) => Ok(s),
+        _ => Err(Error::type_error(
+            format!("'{}' expects a string, found {}", name, value.type_name()),
+            position.clone(),
+        )),
+    }
+}
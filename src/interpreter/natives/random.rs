@@ -0,0 +1,93 @@
+use crate::error::{Error, Position};
+use crate::interpreter::natives::{number_argument, Arity, NativeFunction};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::value::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "random",
+        arity: Arity::exactly(0),
+        function: random,
+    },
+    NativeFunction {
+        name: "seed",
+        arity: Arity::exactly(1),
+        function: seed,
+    },
+];
+
+/// tiny xorshift64* generator, good enough for scripts but NOT for anything security related
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// the seed is scrambled with splitmix64 so nearby seeds give unrelated sequences
+    /// and the state can never be zero (xorshift would only produce zeros)
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng {
+            state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z },
+        }
+    }
+
+    /// seed from the system clock, used when the script never calls seed(n)
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng::new(nanos)
+    }
+
+    /// next number in [0, 1), built from the top 53 bits so every value is exact in a f64
+    pub fn next_f64(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let bits = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        bits as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn random(interpreter: &mut Interpreter, _: &[Value], _: &Position) -> Result<Value, Error> {
+    Ok(Value::Number(interpreter.rng.next_f64()))
+}
+
+fn seed(
+    interpreter: &mut Interpreter,
+    args: &[Value],
+    position: &Position,
+) -> Result<Value, Error> {
+    let n = number_argument("seed", &args[0], position)?;
+    interpreter.rng = Rng::new(n.to_bits());
+    Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::runtime::test_support::run;
+
+    #[test]
+    fn seeding_makes_the_sequence_reproducible() {
+        let program = "seed(42); print random(), random();";
+        let first = run(program).unwrap();
+        assert_eq!(first, run(program).unwrap());
+        assert_ne!(first, run("seed(43); print random(), random();").unwrap());
+    }
+
+    #[test]
+    fn random_numbers_are_in_the_unit_interval() {
+        let mut rng = Rng::new(0);
+        for _ in 0..1000 {
+            let n = rng.next_f64();
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::error::{Error, Position};
+use crate::interpreter::natives::{string_argument, Arity, NativeFunction};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::map::Map;
+use crate::parser::value::{Key, Value};
+
+/// methods called on a string with `s.name(...)`, the receiver is passed as the first argument
+/// and isn't counted in the arity
+pub const METHODS: &[NativeFunction] = &[
+    NativeFunction {
+        name: "upper",
+        arity: Arity::exactly(0),
+        function: upper,
+    },
+    NativeFunction {
+        name: "lower",
+        arity: Arity::exactly(0),
+        function: lower,
+    },
+    NativeFunction {
+        name: "trim",
+        arity: Arity::exactly(0),
+        function: trim,
+    },
+    NativeFunction {
+        name: "split",
+        arity: Arity::exactly(1),
+        function: split,
+    },
+    NativeFunction {
+        name: "contains",
+        arity: Arity::exactly(1),
+        function: contains,
+    },
+];
+
+/// the string method with the given name
+pub fn method(name: &str) -> Option<&'static NativeFunction> {
+    METHODS.iter().find(|method| method.name == name)
+}
+
+fn upper(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let s = string_argument("upper", &args[0], position)?;
+    Ok(Value::String(s.to_uppercase().into()))
+}
+
+fn lower(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let s = string_argument("lower", &args[0], position)?;
+    Ok(Value::String(s.to_lowercase().into()))
+}
+
+fn trim(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let s = string_argument("trim", &args[0], position)?;
+    Ok(Value::String(s.trim().into()))
+}
+
+/// `s.split(sep)`: the pieces between the separators as a map from 0, 1, ... to each piece,
+/// lox has no list type
+fn split(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let s = string_argument("split", &args[0], position)?;
+    let separator = string_argument("split", &args[1], position)?;
+    if separator.is_empty() {
+        return Err(Error::runtime(
+            "'split' separator can't be empty",
+            position.clone(),
+        ));
+    }
+    let pieces = s.split(separator).enumerate().map(|(index, piece)| {
+        let key = Key::Number((index as f64).to_bits());
+        (key, Value::String(piece.into()))
+    });
+    Ok(Value::Map(pieces.collect::<Map>()))
+}
+
+fn contains(_: &mut Interpreter, args: &[Value], position: &Position) -> Result<Value, Error> {
+    let s = string_argument("contains", &args[0], position)?;
+    let sub = string_argument("contains", &args[1], position)?;
+    Ok(Value::Bool(s.contains(sub)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ErrorKind;
+    use crate::interpreter::runtime::test_support::run;
+
+    #[test]
+    fn upper_and_lower_change_the_case() {
+        let output = run("print \"Hello\".upper(), \"Hello\".lower(), \" x \".trim();");
+        assert_eq!(output.unwrap(), "HELLO hello x\n");
+    }
+
+    #[test]
+    fn split_returns_the_pieces_by_index() {
+        let output = run("var parts = \"a,b,,c\".split(\",\"); print parts, len(parts), parts[3];");
+        assert_eq!(
+            output.unwrap(),
+            "{0: \"a\", 1: \"b\", 2: \"\", 3: \"c\"} 4 c\n"
+        );
+        assert_eq!(
+            run("print \"abc\".split(\"-\");").unwrap(),
+            "{0: \"abc\"}\n"
+        );
+    }
+
+    #[test]
+    fn methods_check_their_argument_types() {
+        let error = run("\"a,b\".split(1);").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(error.message, "'split' expects a string, found number");
+
+        let error = run("\"abc\".contains(nil);").unwrap_err();
+        assert_eq!(error.message, "'contains' expects a string, found nil");
+        assert_eq!(
+            run("\"abc\".split(\"\");").unwrap_err().kind,
+            ErrorKind::Runtime
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use crate::error::{Error, Position};
+use crate::interpreter::natives::{Arity, NativeFunction};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::value::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const NATIVES: &[NativeFunction] = &[
+    NativeFunction {
+        name: "clock",
+        arity: Arity::exactly(0),
+        function: clock,
+    },
+    NativeFunction {
+        name: "clock_millis",
+        arity: Arity::exactly(0),
+        function: clock_millis,
+    },
+    NativeFunction {
+        name: "ticks",
+        arity: Arity::exactly(0),
+        function: ticks,
+    },
+];
+
+/// `clock()`: wall-clock seconds since the unix epoch, with the fraction
+fn clock(_: &mut Interpreter, _: &[Value], _: &Position) -> Result<Value, Error> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
+
+/// `clock_millis()`: wall-clock milliseconds since the unix epoch, a whole number
+fn clock_millis(_: &mut Interpreter, _: &[Value], _: &Position) -> Result<Value, Error> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(Value::Number(elapsed.as_millis() as f64))
+}
+
+/// `ticks()`: milliseconds since the interpreter started, with the fraction. unlike the clocks
+/// it never goes back when the system time is changed, so it is the one to time code with
+fn ticks(interpreter: &mut Interpreter, _: &[Value], _: &Position) -> Result<Value, Error> {
+    let elapsed = interpreter.started.elapsed();
+    Ok(Value::Number(elapsed.as_secs_f64() * 1000.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::runtime::test_support::run;
+
+    #[test]
+    fn ticks_never_decrease() {
+        let source = "var a = ticks(); var b = ticks(); var c = ticks(); print b >= a, c >= b;";
+        assert_eq!(run(source).unwrap(), "true true\n");
+    }
+
+    #[test]
+    fn clock_millis_is_a_whole_number_of_milliseconds() {
+        let source = "var ms = clock_millis(); print ms % 1 == 0, ms >= clock() * 1000 - 1000;";
+        assert_eq!(run(source).unwrap(), "true true\n");
+    }
+}
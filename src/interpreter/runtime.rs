@@ -0,0 +1,248 @@
+use crate::error::{Error, Position};
+use crate::interpreter::environment::Environment;
+use crate::interpreter::natives;
+use crate::interpreter::natives::random::Rng;
+use crate::parser::value::Value;
+use std::io;
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+
+/// state shared across the evaluation of expressions
+pub struct Interpreter {
+    pub globals: Environment,
+    pub rng: Rng, // backs random(), reseeded by seed(n)
+    /// when set, arithmetic follows IEEE 754 and may produce inf/NaN (including division by zero),
+    /// otherwise overflowing literals and operations are runtime errors
+    pub ieee_numbers: bool,
+    /// when set, `/` between two whole numbers gives a whole number (`7 / 2` is 3), otherwise
+    /// division is always float division (`7 / 2` is 3.5). `6 / 2` shows as 3 either way since
+    /// numbers are displayed without a trailing ".0", the mode only matters for uneven divisions
+    pub integer_division: bool,
+    /// when set, `+` with a string on either side concatenates the other operand's display form
+    /// (`"count: " + 5`), otherwise mixing strings and other types is an error
+    pub coerce_strings: bool,
+    /// when set, boolean contexts (`!`, the ternary condition) only accept booleans,
+    /// otherwise lox's loose truthiness applies
+    pub strict_truthiness: bool,
+    /// when set, reading a key missing from a map is an error, otherwise it reads as nil
+    pub strict_keys: bool,
+    /// when set, numbers are shown with this many significant digits (`3.14` for pi at 3),
+    /// otherwise with the shortest form that reads back as the same number
+    pub precision: Option<usize>,
+    /// when set, every node is written to the output before it is evaluated and its value after
+    pub trace: bool,
+    pub(crate) depth: usize, // nesting of the node being evaluated, indents the trace
+    /// where program output goes, stdout unless replaced (e.g. by a buffer in tests)
+    pub output: Box<dyn Write>,
+    /// where read_line() reads from, `None` reads stdin (shared with the REPL, so no lock is held)
+    pub input: Option<Box<dyn BufRead>>,
+    /// wall-clock budget of a single run, useful to sandbox untrusted scripts
+    pub timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    pub(crate) started: Instant, // when the interpreter was created, ticks() counts from here
+}
+
+impl Interpreter {
+    /// create an interpreter whose global environment already holds the natives
+    pub fn new() -> Self {
+        let mut globals = Environment::new();
+        natives::install(&mut globals);
+        Interpreter {
+            globals,
+            rng: Rng::from_time(),
+            ieee_numbers: false,
+            integer_division: false,
+            coerce_strings: false,
+            strict_truthiness: false,
+            strict_keys: false,
+            precision: None,
+            trace: false,
+            depth: 0,
+            output: Box::new(io::stdout()),
+            input: None,
+            timeout: None,
+            deadline: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// forget every variable and constant, leaving only the natives. options, the output and
+    /// the random generator are kept
+    pub fn reset(&mut self) {
+        self.globals = Environment::new();
+        natives::install(&mut self.globals);
+    }
+
+    /// start the clock of a new run, the deadline is `timeout` from now
+    pub fn start_deadline(&mut self) {
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    /// fail once the deadline has passed, checked on calls (and loops) to keep the overhead low
+    pub(crate) fn check_deadline(&self, position: &Position) -> Result<(), Error> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                Err(Error::runtime("Execution timed out", position.clone())
+                    .with_help("The script ran longer than the configured timeout"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// truthiness of a value used as a condition, a type error for non-booleans in strict mode
+    pub fn is_truthy(&self, value: &Value, position: Position) -> Result<bool, Error> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            _ if self.strict_truthiness => Err(Error::type_error(
+                format!("Expected a boolean condition, found {}", value.type_name()),
+                position,
+            )
+            .with_help("Strict truthiness is enabled, compare the value explicitly")),
+            _ => Ok(value.is_truthy()),
+        }
+    }
+
+    /// the text a value is shown as, following `precision`
+    pub fn display(&self, value: &Value) -> String {
+        match self.precision {
+            Some(digits) => value.round_to(digits).to_string(),
+            None => value.to_string(),
+        }
+    }
+
+    /// write a line to the output sink, a failing sink is not an error of the program
+    pub fn write_line(&mut self, line: &str) {
+        let _ = writeln!(self.output, "{}", line);
+    }
+
+    /// write a trace line indented by the current evaluation depth
+    pub(crate) fn trace_line(&mut self, line: &str) {
+        let indent = "  ".repeat(self.depth);
+        self.write_line(&format!("{}{}", indent, line));
+    }
+}
+
+/// helpers shared by the tests that run lox source
+#[cfg(test)]
+pub(crate) mod test_support {
+    use crate::error::Error;
+    use crate::interpreter::lox::Lox;
+    use std::cell::RefCell;
+    use std::io;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    /// an output sink the test keeps a handle on, to read back what the program wrote
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl SharedBuffer {
+        pub(crate) fn contents(&self) -> String {
+            String::from_utf8_lossy(&self.0.borrow()).into_owned()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// a Lox whose program output goes to the returned buffer
+    pub(crate) fn lox() -> (Lox, SharedBuffer) {
+        let mut lox = Lox::new();
+        let output = SharedBuffer::default();
+        lox.interpreter.output = Box::new(output.clone());
+        (lox, output)
+    }
+
+    /// run the source in a fresh Lox and return everything it printed
+    pub(crate) fn run(source: &str) -> Result<String, Error> {
+        let (mut lox, output) = lox();
+        lox.run(source)?;
+        Ok(output.contents())
+    }
+
+    /// write a source file for the tests that go through a path, named after the test so
+    /// tests running in parallel don't share one
+    pub(crate) fn source_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("lox-{}-{}.lox", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::lox;
+    use crate::error::ErrorKind;
+    use std::time::Duration;
+
+    #[test]
+    fn calls_past_the_deadline_time_out() {
+        let (mut lox, output) = lox();
+        lox.interpreter.timeout = Some(Duration::ZERO);
+        let error = lox.run("print 1; print max(1, 2);").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "Execution timed out");
+        assert_eq!(output.contents(), "1\n");
+
+        // each run gets the whole timeout again
+        lox.interpreter.timeout = Some(Duration::from_secs(60));
+        lox.run("print max(1, 2);").unwrap();
+        assert_eq!(output.contents(), "1\n2\n");
+    }
+
+    #[test]
+    fn strict_truthiness_only_accepts_booleans() {
+        let (mut lox, output) = lox();
+        lox.run("print 1 ? \"yes\" : \"no\"; print !nil;").unwrap();
+        assert_eq!(output.contents(), "yes\ntrue\n");
+
+        lox.interpreter.strict_truthiness = true;
+        lox.run("print true ? \"yes\" : \"no\"; print !false;")
+            .unwrap();
+        for source in ["1 ? 2 : 3;", "!nil;"] {
+            let error = lox.run(source).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::Type);
+            assert!(error.message.starts_with("Expected a boolean condition"));
+        }
+    }
+
+    #[test]
+    fn globals_last_across_runs_until_reset() {
+        let (mut lox, output) = lox();
+        lox.run("var x = 1; const c = 2;").unwrap();
+        lox.run("print x + 1;").unwrap();
+        assert_eq!(output.contents(), "2\n");
+
+        lox.interpreter.reset();
+        assert_eq!(lox.run("x;").unwrap_err().message, "Undefined variable 'x'");
+        // the constant is gone too, and the natives are back
+        lox.run("var c = 3; print max(c, 1);").unwrap();
+        assert_eq!(output.contents(), "2\n3\n");
+    }
+
+    #[test]
+    fn precision_sets_the_significant_digits_printed() {
+        let source = "print 3.141592653589793, 1234.5, 0.000123456, {1: 2.71828};";
+        let (mut lox, output) = lox();
+        lox.run(source).unwrap();
+        lox.interpreter.precision = Some(3);
+        lox.run(source).unwrap();
+        lox.interpreter.precision = Some(1);
+        lox.run(source).unwrap();
+        let expected = [
+            "3.141592653589793 1234.5 0.000123456 {1: 2.71828}",
+            "3.14 1230 0.000123 {1: 2.72}",
+            "3 1000 0.0001 {1: 3}",
+        ];
+        assert_eq!(output.contents(), expected.join("\n") + "\n");
+    }
+}
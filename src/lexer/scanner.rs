@@ -1,6 +1,7 @@
 use crate::error;
-use crate::error::{Error, ErrorKind, Position};
+use crate::error::{Diagnostics, Error, Position};
 use crate::token::{Token, TokenKind};
+use crate::utils::line_index::{bom_length, LineIndex};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 
@@ -20,64 +21,143 @@ lazy_static! {
         m.insert("print", TokenKind::Print);
         m.insert("return", TokenKind::Return);
         m.insert("super", TokenKind::Super);
+        m.insert("this", TokenKind::This);
         m.insert("true", TokenKind::True);
         m.insert("var", TokenKind::Var);
         m.insert("while", TokenKind::While);
+        m.insert("switch", TokenKind::Switch);
+        m.insert("case", TokenKind::Case);
+        m.insert("default", TokenKind::Default);
+        m.insert("const", TokenKind::Const);
+        m.insert("in", TokenKind::In);
+        m.insert("typeof", TokenKind::TypeOf);
         m
     };
 }
 pub struct Scanner {
-    source: String,
+    index: LineIndex<'static>, // the source, and the line and column of any offset into it
     tokens: Vec<Token>,
-    start: usize,   // points to the first character of the lexeme => offset
-    current: usize, // points at the character currently being considered => offset
-    line: usize,    // track what source line current is on.
-    column: usize,  // current column
+    start: usize,        // points to the first character of the lexeme => offset
+    current: usize,      // points at the character currently being considered => offset
+    first_column: usize, // column the source starts at on its first line
+    keep_comments: bool, // emit comments as tokens instead of skipping them
+    forbid_tabs: bool,   // a tab in the indentation of a line is a syntax error
+    keywords: Option<HashMap<String, TokenKind>>, // custom keyword table, `KEYWORDS` when None
 }
 
 impl Scanner {
     pub fn new(source: String, tokens: Vec<Token>, column: usize) -> Self {
+        // some editors save files with a leading BOM, scanning starts past it so it isn't an
+        // unexpected character. the source stays whole so offsets still index into it
+        let bom = bom_length(&source);
         Scanner {
-            source,
+            index: LineIndex::new(source),
             tokens,
-            start: 0,
-            current: 0,
-            line: 1,
-            column,
+            start: bom,
+            current: bom,
+            first_column: column,
+            keep_comments: false,
+            forbid_tabs: false,
+            keywords: None,
         }
     }
+
+    /// scan with a custom keyword table, e.g. to teach the language in another tongue
+    /// (`stampa` for `print`, `se` for `if`). words missing from the table are identifiers
+    pub fn with_keywords(mut self, keywords: HashMap<String, TokenKind>) -> Self {
+        self.keywords = Some(keywords);
+        self
+    }
+
+    /// the english keyword table, a starting point for a localized one
+    pub fn default_keywords() -> HashMap<String, TokenKind> {
+        KEYWORDS
+            .iter()
+            .map(|(word, kind)| (word.to_string(), *kind))
+            .collect()
+    }
+
+    /// width of a tab stop used when counting columns, so carets line up with editors (default 4)
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.index = self.index.tab_width(width);
+        self
+    }
+
+    /// keep comments as `TokenKind::Comment` tokens, useful for tooling like a formatter
+    pub fn keep_comments(mut self, keep: bool) -> Self {
+        self.keep_comments = keep;
+        self
+    }
+
+    /// report every tab used to indent a line as a syntax error, for setups that only allow
+    /// spaces. tabs after the first token of a line are still fine (off by default)
+    pub fn forbid_tabs(mut self, forbid: bool) -> Self {
+        self.forbid_tabs = forbid;
+        self
+    }
+
     /// Method used to check all the characters were consumed
     fn is_at_the_end(&self) -> bool {
-        self.current >= self.source.len()
+        self.current >= self.source().len()
+    }
+
+    fn source(&self) -> &str {
+        self.index.source()
+    }
+
+    /// where the char at `offset` is, worked out from the offset alone so the scan loop only
+    /// has to move through the source
+    fn position(&self, offset: usize) -> Position {
+        let (line, column) = self.index.position(offset);
+        match line {
+            1 => Position::new(line, column + self.first_column - 1, offset),
+            _ => Position::new(line, column, offset),
+        }
     }
 
     /// Method used to scan the source code and return all the tokens
     pub fn get_tokens(&mut self) -> Result<Vec<Token>, Error> {
+        let mut diagnostics = Diagnostics::new();
+        let tokens = self.scan_with_diagnostics(&mut diagnostics);
+        match diagnostics.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(tokens),
+        }
+    }
+
+    /// scan the whole source, recording every error and skipping past it instead of stopping
+    pub fn scan_with_diagnostics(&mut self, diagnostics: &mut Diagnostics) -> Vec<Token> {
+        self.skip_shebang();
         while !self.is_at_the_end() {
             self.start = self.current;
-            match self.scan_token() {
-                Ok(_) => {}
-                Err(e) => return Err(e),
-            };
+            // every error leaves the offending characters consumed, so scanning moves on
+            if let Err(error) = self.scan_token() {
+                diagnostics.push(error);
+            }
         }
-        // push EOF token to the vector
+        // the EOF token sits one past the last character, where "expected ... before the end"
+        // errors should point. `start` would be the last token (or whitespace) scanned instead
+        let end = self.position(self.current);
         self.tokens.push(Token::new(
             String::from(""),
             TokenKind::Eof,
-            self.line,
-            self.column,
-            self.start,
+            end.line,
+            end.column,
+            self.current,
         ));
-        Ok(self.tokens.clone())
+        self.tokens.clone()
     }
 
     fn add_token(&mut self, token_kind: TokenKind, value: Option<String>) {
         let lexeme = match value {
             Some(value) => value,
-            None => self.source[self.start..self.current].to_string(),
+            None => self.source()[self.start..self.current].to_string(),
         };
-        self.tokens
-            .push(Token::new(lexeme, token_kind, self.line, self.column, self.start));
+        let Position { line, column, .. } = self.position(self.start);
+        let mut token = Token::new(lexeme, token_kind, line, column, self.start);
+        // a string loses its quotes and a number is normalized, the length still covers the source
+        token.length = self.current - self.start;
+        self.tokens.push(token);
     }
 
     fn scan_token(&mut self) -> Result<(), Error> {
@@ -85,21 +165,35 @@ impl Scanner {
             '+' => self.add_token(TokenKind::Plus, None),
             '-' => self.add_token(TokenKind::Minus, None),
             '*' => self.add_token(TokenKind::Star, None),
-            '/' => match self.peek_match('/') {
-                true => {
+            '/' => {
+                if self.peek_match('/') {
                     //  A comment goes until the end of the line
                     while self.peek() != '\n' && !self.is_at_the_end() {
                         self.advance();
                     }
+                    self.add_comment();
+                } else if self.peek_match('*') {
+                    self.handle_block_comment()?;
+                    self.add_comment();
+                } else {
+                    self.add_token(TokenKind::Slash, None);
                 }
-                false => self.add_token(TokenKind::Slash, None),
-            },
+            }
             '(' => self.add_token(TokenKind::LeftParen, None),
             ')' => self.add_token(TokenKind::RightParen, None),
             '{' => self.add_token(TokenKind::LeftBrace, None),
             '}' => self.add_token(TokenKind::RightBrace, None),
+            '[' => self.add_token(TokenKind::LeftBracket, None),
+            ']' => self.add_token(TokenKind::RightBracket, None),
             ',' => self.add_token(TokenKind::Comma, None),
-            '.' => self.add_token(TokenKind::Dot, None),
+            ';' => self.add_token(TokenKind::Semicolon, None),
+            '.' => match self.peek_match('.') {
+                true => self.add_token(TokenKind::DotDot, None),
+                false => self.add_token(TokenKind::Dot, None),
+            },
+            '?' => self.add_token(TokenKind::Question, None),
+            '~' => self.add_token(TokenKind::Tilde, None),
+            ':' => self.add_token(TokenKind::Colon, None),
             '!' => match self.peek_match('=') {
                 true => self.add_token(TokenKind::BangEqual, None),
                 false => self.add_token(TokenKind::Bang, None),
@@ -112,118 +206,217 @@ impl Scanner {
                 true => self.add_token(TokenKind::LessEqual, None),
                 false => self.add_token(TokenKind::Less, None),
             },
+            '%' => match self.peek_match('=') {
+                true => self.add_token(TokenKind::PercentEqual, None),
+                false => self.add_token(TokenKind::Percent, None),
+            },
             '=' => match self.peek_match('=') {
                 true => self.add_token(TokenKind::EqualEqual, None),
                 false => self.add_token(TokenKind::Equal, None),
             },
             ' ' => {}
             '\r' => {}
+            '\t' if self.forbid_tabs && self.is_indentation() => {
+                return Err(
+                    Error::syntax("Tab used for indentation", self.position(self.start))
+                        .with_help("Indent with spaces instead"),
+                );
+            }
             '\t' => {}
-            '\n' => self.line += 1,               // move line
+            '\n' => {}
             '"' => self.handle_string_literal()?, // return early error
-            'o' => {
-                if self.peek_match('r') {
-                    self.add_token(TokenKind::Or, None);
-                }
-            }
-            _ => {
-                if self.peek().is_ascii_digit() {
-                    self.handle_number_literal();
-                } else if self.peek().is_ascii_alphabetic() {
+            'b' if self.peek() == '"' => self.handle_bytes_literal()?,
+            c => {
+                if c.is_ascii_digit() {
+                    self.handle_number_literal()?;
+                } else if Self::is_alphabetic(c) {
                     self.handle_identifier();
+                } else {
+                    return Err(Error::syntax(
+                        "Unexpected character.",
+                        self.position(self.start),
+                    ));
                 }
-
-                return Err(Error::parse(
-                    "Unexpected character.",
-                    Position::new(self.line, self.column, self.current),
-                ));
             }
         }
         Ok(())
     }
 
-    /// this method will consume the next character of the source by incrementing the position by one
+    /// scripts run directly start with `#!/usr/bin/env lox`, that line is skipped like a comment.
+    /// only the very first line is checked, a `#` anywhere else is still an error
+    fn skip_shebang(&mut self) {
+        let at_start = self.current == bom_length(self.source());
+        if at_start && self.source()[self.current..].starts_with("#!") {
+            while self.peek() != '\n' && !self.is_at_the_end() {
+                self.advance();
+            }
+        }
+    }
+
+    /// this method will consume the next character of the source by moving the position past it
     fn advance(&mut self) -> char {
-        let c = self.source[self.current..].chars().next().unwrap();
-        self.current += 1;
+        let c = self.peek();
+        self.current += c.len_utf8(); // offsets are in bytes, a char may take more than one
         c
     }
 
     /// this method will peek the next character but NOT consume the toke => Lookahead
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-        self.source[self.current + 1..].chars().next().unwrap()
+        self.source()[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     /// this method will peek the current char but NOT consume the token => Lookahead.
     fn peek(&self) -> char {
-        if self.is_at_the_end() {
-            '\0';
-        }
-        self.source[self.current..].chars().next().unwrap()
+        self.source()[self.current..].chars().next().unwrap_or('\0')
     }
 
     /// peek match will check if the given `char` is the same as the next one then return true and update the position, otherwise false
     fn peek_match(&mut self, next: char) -> bool {
         if self.is_at_the_end() || self.peek() != next {
-            false;
+            return false;
         }
-        self.current += 1;
+        self.advance();
         true
     }
 
     /// this method will iterate through the lexeme, then it will parse the lexeme to find a number-token
-    fn handle_number_literal(&mut self) {
+    fn handle_number_literal(&mut self) -> Result<(), Error> {
         while self.peek().is_ascii_digit() && !self.is_at_the_end() {
             self.advance();
         }
-        // decimal part
-        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+        // decimal part, a second one (`1.2.3`) makes the literal invalid
+        while self.peek() == '.' && self.peek_next().is_ascii_digit() {
+            self.advance();
+            while self.peek().is_ascii_digit() {
+                self.advance();
+            }
+        }
+        // exponent, `1e3` or `1e-3`
+        if matches!(self.peek(), 'e' | 'E') {
+            self.advance();
+            if matches!(self.peek(), '+' | '-') && self.peek_next().is_ascii_digit() {
+                self.advance();
+            }
+            while self.peek().is_ascii_digit() {
+                self.advance();
+            }
+        }
+        // letters glued to the number (`1e`, `12ab`, `1_000`) belong to the literal, which is
+        // then rejected as a whole instead of splitting off an identifier
+        while Self::is_alphanumeric(self.peek()) {
             self.advance();
         }
-        let value = self.source[self.start..self.current]
-            .to_string()
-            .parse::<f64>()
-            .unwrap();
 
+        let lexeme = &self.source()[self.start..self.current];
+        let Ok(value) = lexeme.parse::<f64>() else {
+            return Err(Error::syntax(
+                format!("Invalid number literal '{}'", lexeme),
+                self.position(self.start),
+            ));
+        };
         self.add_token(TokenKind::Number, Some(value.to_string()));
+        Ok(())
+    }
+
+    /// comments are dropped unless the scanner was asked to keep them
+    fn add_comment(&mut self) {
+        if self.keep_comments {
+            self.add_token(TokenKind::Comment, None);
+        }
+    }
+
+    /// this method will consume a `/* ... */` comment, the opening `/*` is already consumed
+    fn handle_block_comment(&mut self) -> Result<(), Error> {
+        while !(self.peek() == '*' && self.peek_next() == '/') {
+            if self.is_at_the_end() {
+                return Err(Error::syntax(
+                    "Unterminated block comment",
+                    self.position(self.current),
+                ));
+            }
+            self.advance();
+        }
+        self.advance(); // the closing */
+        self.advance();
+        Ok(())
     }
 
     /// this method will iterate through the lexeme, then it will parse the lexeme to find a string-token
     fn handle_string_literal(&mut self) -> Result<(), Error> {
         while self.peek() != '"' && !self.is_at_the_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-            }
             self.advance();
         }
         if self.is_at_the_end() {
             return Err(Error::syntax(
                 "Unterminated string literal",
-                Position::new(self.line, self.column, self.current),
+                self.position(self.current),
             ));
         }
         self.advance(); // the closing " of the string literal
         // Trim the surrounding quotes
-        let value = self.source[(self.start + 1)..(self.current + 1)].to_string();
+        let raw = &self.source()[(self.start + 1)..(self.current - 1)];
+        let value = unescape_unicode(raw)
+            .map_err(|message| Error::syntax(message, self.position(self.start)))?;
         self.add_token(TokenKind::String, Some(value));
         Ok(())
     }
 
+    /// scan a `b"..."` byte string, the `b` is already consumed. escapes are checked here and
+    /// decoded again by the parser with `unescape_bytes`
+    fn handle_bytes_literal(&mut self) -> Result<(), Error> {
+        self.advance(); // the opening "
+        while self.peek() != '"' && !self.is_at_the_end() {
+            if self.peek() == '\\' {
+                self.advance(); // skip the backslash so an escaped quote doesn't end the literal
+                if self.is_at_the_end() {
+                    break;
+                }
+            }
+            self.advance();
+        }
+        if self.is_at_the_end() {
+            return Err(Error::syntax(
+                "Unterminated byte string literal",
+                self.position(self.current),
+            ));
+        }
+        self.advance(); // the closing "
+
+        let raw = self.source()[(self.start + 2)..(self.current - 1)].to_string();
+        if let Err(message) = unescape_bytes(&raw) {
+            return Err(Error::syntax(message, self.position(self.start)));
+        }
+        self.add_token(TokenKind::Bytes, Some(raw));
+        Ok(())
+    }
+
     /// this method will be used to handle the type-identifier token
     fn handle_identifier(&mut self) {
-        while self.peek().is_alphanumeric() && !self.is_at_the_end() {
+        while Self::is_alphanumeric(self.peek()) && !self.is_at_the_end() {
             self.advance();
         }
 
-        let text = self.source[self.start..self.current].trim();
-        let token_kind = KEYWORDS.get(text).cloned().unwrap_or(TokenKind::Identifier);
+        let text = self.source()[self.start..self.current].trim();
+        let keyword = match &self.keywords {
+            Some(keywords) => keywords.get(text).cloned(),
+            None => KEYWORDS.get(text).cloned(),
+        };
+        let token_kind = keyword.unwrap_or(TokenKind::Identifier);
 
         self.add_token(token_kind, None);
     }
 
+    /// whether only spaces and tabs come before the current lexeme on its line
+    fn is_indentation(&self) -> bool {
+        let line_start = match self.source()[..self.start].rfind('\n') {
+            Some(newline) => newline + 1,
+            None => bom_length(self.source()),
+        };
+        self.source()[line_start..self.start]
+            .chars()
+            .all(|c| c == ' ' || c == '\t')
+    }
+
     /// useful wrappers
     fn is_alphanumeric(c: char) -> bool {
         c.is_alphanumeric() || c == '_'
@@ -232,3 +425,323 @@ impl Scanner {
         c.is_alphabetic() || c == '_'
     }
 }
+
+/// decode the `\u{...}` escapes of a string body into the characters they name (1 to 6 hex
+/// digits, a unicode scalar value). strings have no other escapes, any other `\` is kept as is
+pub fn unescape_unicode(raw: &str) -> Result<String, String> {
+    let mut text = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(index) = rest.find("\\u") {
+        text.push_str(&rest[..index]);
+        rest = &rest[index + 2..];
+        let hex = match rest.strip_prefix('{').and_then(|body| body.split_once('}')) {
+            Some((hex, after)) => {
+                rest = after;
+                hex
+            }
+            None => return Err("Invalid escape '\\u', expected '\\u{' hex digits '}'".to_string()),
+        };
+        if hex.is_empty() || hex.len() > 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "Invalid escape '\\u{{{}}}', expected 1 to 6 hex digits",
+                hex
+            ));
+        }
+        match char::from_u32(u32::from_str_radix(hex, 16).unwrap()) {
+            Some(c) => text.push(c),
+            None => {
+                return Err(format!(
+                    "Invalid escape '\\u{{{}}}', not a unicode scalar value",
+                    hex
+                ))
+            }
+        }
+    }
+    text.push_str(rest);
+    Ok(text)
+}
+
+/// decode the body of a byte string: `\xHH`, `\n`, `\r`, `\t`, `\0`, `\\` and `\"` escapes,
+/// any other character stands for its utf-8 bytes
+pub fn unescape_bytes(raw: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        let byte = match chars.next() {
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('t') => b'\t',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('"') => b'"',
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if hex.len() != 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!(
+                        "Invalid escape '\\x{}', expected two hex digits",
+                        hex
+                    ));
+                }
+                u8::from_str_radix(&hex, 16).unwrap()
+            }
+            Some(other) => return Err(format!("Unknown escape '\\{}' in byte string", other)),
+            None => return Err("Byte string ends in the middle of an escape".to_string()),
+        };
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::interpreter::lox::Lox;
+    use crate::interpreter::natives::random::Rng;
+    use crate::interpreter::runtime::test_support::run;
+
+    fn scan(source: &str) -> Result<Vec<Token>, Error> {
+        Scanner::new(source.to_string(), Vec::new(), 1).get_tokens()
+    }
+
+    fn kinds(tokens: &[Token]) -> Vec<TokenKind> {
+        tokens.iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn shebang_line_is_skipped() {
+        let output = run("#!/usr/bin/env lox\nprint 1 + 2;\n").unwrap();
+        assert_eq!(output, "3\n");
+        let tokens = scan("#!/usr/bin/env lox\n1").unwrap();
+        assert_eq!(kinds(&tokens), [TokenKind::Number, TokenKind::Eof]);
+        assert_eq!(tokens[0].line, 2);
+    }
+
+    #[test]
+    fn shebang_only_counts_on_the_first_line() {
+        assert!(scan("1\n#!/usr/bin/env lox").is_err());
+    }
+
+    #[test]
+    fn custom_keyword_table_replaces_the_english_one() {
+        let mut keywords = HashMap::new();
+        keywords.insert("stampa".to_string(), TokenKind::Print);
+        keywords.insert("nullo".to_string(), TokenKind::Nil);
+        let tokens = Scanner::new("stampa nullo print".to_string(), Vec::new(), 1)
+            .with_keywords(keywords)
+            .get_tokens()
+            .unwrap();
+        assert_eq!(
+            kinds(&tokens),
+            [
+                TokenKind::Print,
+                TokenKind::Nil,
+                TokenKind::Identifier,
+                TokenKind::Eof
+            ]
+        );
+        assert_eq!(Scanner::default_keywords()["print"], TokenKind::Print);
+    }
+
+    #[test]
+    fn malformed_numbers_are_echoed_in_the_error() {
+        for lexeme in ["1e", "1.2.3", "12ab"] {
+            let error = scan(&format!("print {};", lexeme)).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::Syntax);
+            let message = format!("Invalid number literal '{}'", lexeme);
+            assert_eq!(error.message, message);
+            assert_eq!(error.position.column, 7);
+        }
+        // two dots in a row are a range, not a malformed number
+        let tokens = scan("1..2 1e3 2.5e-1").unwrap();
+        assert_eq!(
+            kinds(&tokens),
+            [
+                TokenKind::Number,
+                TokenKind::DotDot,
+                TokenKind::Number,
+                TokenKind::Number,
+                TokenKind::Number,
+                TokenKind::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn positions_follow_offsets_across_lines_and_wide_chars() {
+        let source = "\"a\nb\" 1\n\"é😀\" 2 /* x\n*/ 3";
+        let tokens = scan(source).unwrap();
+        let positions: Vec<(usize, usize)> = tokens
+            .iter()
+            .map(|token| (token.line, token.column))
+            .collect();
+        // a multi-line string is where it starts, columns count chars rather than bytes
+        assert_eq!(positions, [(1, 1), (2, 4), (3, 1), (3, 6), (4, 4), (4, 5)]);
+        assert_eq!(tokens[3].offset, source.find('2').unwrap());
+    }
+
+    #[test]
+    fn leading_bom_is_skipped_and_offsets_still_match_the_source() {
+        let source = "\u{FEFF}print 1;";
+        let tokens = scan(source).unwrap();
+        assert_eq!(
+            kinds(&tokens),
+            [
+                TokenKind::Print,
+                TokenKind::Number,
+                TokenKind::Semicolon,
+                TokenKind::Eof
+            ]
+        );
+        assert_eq!((tokens[0].line, tokens[0].column), (1, 1));
+        assert_eq!(tokens[0].offset, 3);
+        assert_eq!(tokens[0].span().slice(source), "print");
+        assert_eq!(tokens[1].to_debug_line(source), "Number[1:7] \"1\"");
+        assert_eq!(run(source).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn bom_before_a_shebang() {
+        assert_eq!(run("\u{FEFF}#!/usr/bin/env lox\nprint 1;").unwrap(), "1\n");
+    }
+
+    #[test]
+    fn comments_become_tokens_only_when_kept() {
+        let source = "1 // hi\n/* block\ncomment */ 2";
+        let tokens = scan(source).unwrap();
+        assert_eq!(
+            kinds(&tokens),
+            [TokenKind::Number, TokenKind::Number, TokenKind::Eof]
+        );
+
+        let tokens = Scanner::new(source.to_string(), Vec::new(), 1)
+            .keep_comments(true)
+            .get_tokens()
+            .unwrap();
+        assert_eq!(
+            kinds(&tokens),
+            [
+                TokenKind::Number,
+                TokenKind::Comment,
+                TokenKind::Comment,
+                TokenKind::Number,
+                TokenKind::Eof
+            ]
+        );
+        assert_eq!(tokens[1].lexeme, "// hi");
+        assert_eq!((tokens[1].line, tokens[1].column), (1, 3));
+        assert_eq!(tokens[2].lexeme, "/* block\ncomment */");
+        assert_eq!(tokens[2].line, 2);
+    }
+
+    #[test]
+    fn tabs_advance_to_the_next_tab_stop() {
+        let tokens = scan("\tx\n  \ty").unwrap();
+        assert_eq!((tokens[0].line, tokens[0].column), (1, 5));
+        assert_eq!((tokens[1].line, tokens[1].column), (2, 5));
+
+        let tokens = Scanner::new("\tx".to_string(), Vec::new(), 1)
+            .tab_width(2)
+            .get_tokens()
+            .unwrap();
+        assert_eq!(tokens[0].column, 3);
+    }
+
+    /// inputs that used to make the scanner panic, or are easy to get wrong
+    const TRICKY_INPUTS: &[&str] = &[
+        "",
+        "\"",
+        "\"unterminated",
+        "/",
+        "//",
+        "/*",
+        "/* unterminated *",
+        "1.",
+        "1e",
+        "1e+",
+        ".5",
+        "b\"",
+        "b\"\\",
+        "b\"\\x4",
+        "\"\\u{\"",
+        "\"\\u{110000}\"",
+        "\u{FEFF}",
+        "#!",
+        "é",
+        "\"é",
+        "\u{1F600}\u{1F600}",
+        "\0",
+        "\r\n\t",
+    ];
+
+    #[test]
+    fn scanner_never_panics() {
+        for input in TRICKY_INPUTS {
+            let _ = scan(input);
+        }
+
+        // random bytes, made into text the way a lossy reader would
+        let mut rng = Rng::new(618);
+        for _ in 0..2000 {
+            let length = (rng.next_f64() * 16.0) as usize;
+            let bytes: Vec<u8> = (0..length)
+                .map(|_| (rng.next_f64() * 256.0) as u8)
+                .collect();
+            let _ = scan(&String::from_utf8_lossy(&bytes));
+        }
+
+        // random strings of the characters the scanner treats specially, which reach much
+        // deeper than random bytes. they go through the parser too
+        let alphabet: Vec<char> = "\"/*\\bu{}x1e.+-\n\té\u{FEFF}#! ".chars().collect();
+        let lox = Lox::new();
+        for _ in 0..5000 {
+            let length = (rng.next_f64() * 12.0) as usize;
+            let input: String = (0..length)
+                .map(|_| alphabet[(rng.next_f64() * alphabet.len() as f64) as usize])
+                .collect();
+            let mut diagnostics = Diagnostics::new();
+            let tokens =
+                Scanner::new(input.clone(), Vec::new(), 1).scan_with_diagnostics(&mut diagnostics);
+            assert_eq!(tokens.last().map(|token| token.kind), Some(TokenKind::Eof));
+            let _ = lox.parse_with_diagnostics(&input);
+        }
+    }
+
+    #[test]
+    fn eof_sits_one_past_the_last_character() {
+        let source = "print 1;\nprint (2";
+        let tokens = Lox::new().tokenize(source).unwrap();
+        let eof = tokens.last().unwrap();
+        assert_eq!(eof.kind, TokenKind::Eof);
+        assert_eq!((eof.line, eof.column), (source.lines().count(), 9));
+        assert_eq!(eof.offset, source.len());
+
+        let error = Lox::new().run(source).unwrap_err();
+        assert_eq!((error.position.line, error.position.column), (2, 9));
+    }
+
+    #[test]
+    fn unicode_escapes_decode_to_their_char() {
+        assert_eq!(run("print \"caf\\u{e9}\";").unwrap(), "café\n");
+        assert_eq!(run("print \"\\u{1F600}!\";").unwrap(), "😀!\n");
+        assert_eq!(unescape_unicode("a\\nb").unwrap(), "a\\nb"); // not an escape here
+
+        for (source, problem) in [
+            ("\"\\u{D800}\"", "'\\u{D800}', not a unicode scalar value"),
+            ("\"\\u{}\"", "'\\u{}', expected 1 to 6 hex digits"),
+            ("\"\\u41\"", "'\\u', expected '\\u{' hex digits '}'"),
+        ] {
+            let error = run(source).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::Syntax, "{}", source);
+            assert_eq!(error.message, format!("Invalid escape {}", problem));
+        }
+        // past the last code point
+        let error = unescape_unicode("\\u{110000}").unwrap_err();
+        assert!(error.ends_with("not a unicode scalar value"));
+    }
+}
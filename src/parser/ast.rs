@@ -0,0 +1,410 @@
+use crate::error::{Error, Position};
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::expr::{Expr, Notation};
+use crate::parser::value::Value;
+use crate::token::{Span, Token};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/*
+   program = statement* EOF;
+   statement = varDecl | constDecl | exprStmt | printStmt | switchStmt | forStmt;
+   varDecl = "var" IDENTIFIER ( "=" expression )? ";";
+   constDecl = "const" IDENTIFIER "=" expression ";";
+   exprStmt = expression ";";
+   printStmt = "print" expression ( "," expression )* ";";
+   switchStmt = "switch" "(" expression ")" "{" ( "case" expression ":" statement* )*
+                ( "default" ":" statement* )? "}";
+   forStmt = "for" "(" IDENTIFIER "in" expression ")" statement;
+*/
+
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub enum Stmt {
+    Expression {
+        expr: Expr,
+        semicolon: Option<Token>, // None for a trailing expression, whose value the REPL prints
+    },
+    Print {
+        keyword: Token,
+        values: Vec<Expr>, // at least one, printed on one line separated by spaces
+        semicolon: Token,
+    },
+    Var {
+        keyword: Token,
+        name: Token,
+        initializer: Option<Expr>, // a variable declared without one starts as nil
+        semicolon: Token,
+    },
+    Const {
+        keyword: Token,
+        name: Token,
+        initializer: Expr,
+        semicolon: Token,
+    },
+    Switch {
+        keyword: Token,
+        subject: Expr,
+        cases: Vec<(Expr, Vec<Stmt>)>,
+        default: Option<Vec<Stmt>>,
+        closing: Token,
+    },
+    ForEach {
+        keyword: Token,
+        name: Token,
+        iterable: Expr,
+        body: Box<Stmt>,
+    },
+}
+
+impl Stmt {
+    pub fn execute(&self, interpreter: &mut Interpreter) -> Result<(), Error> {
+        if !interpreter.trace {
+            return self.execute_node(interpreter);
+        }
+
+        interpreter.trace_line(&self.trace_text());
+        interpreter.depth += 1;
+        let result = self.execute_node(interpreter);
+        interpreter.depth -= 1;
+        result
+    }
+
+    /// what `--trace` shows for the statement, its nested statements are traced as they run
+    fn trace_text(&self) -> String {
+        let lisp = |expr: &Expr| expr.print(Notation::Lisp);
+        match self {
+            Stmt::Expression { expr, .. } => format!("(expression {})", lisp(expr)),
+            Stmt::Print { values, .. } => {
+                let values: Vec<String> = values.iter().map(lisp).collect();
+                format!("(print {})", values.join(" "))
+            }
+            Stmt::Var {
+                name,
+                initializer: Some(initializer),
+                ..
+            } => format!("(var {} {})", name.lexeme, lisp(initializer)),
+            Stmt::Var { name, .. } => format!("(var {})", name.lexeme),
+            Stmt::Const {
+                name, initializer, ..
+            } => format!("(const {} {})", name.lexeme, lisp(initializer)),
+            Stmt::Switch { subject, .. } => format!("(switch {})", lisp(subject)),
+            Stmt::ForEach { name, iterable, .. } => {
+                format!("(for {} {})", name.lexeme, lisp(iterable))
+            }
+        }
+    }
+
+    fn execute_node(&self, interpreter: &mut Interpreter) -> Result<(), Error> {
+        match self {
+            Stmt::Expression { expr, .. } => {
+                expr.evaluate_in(interpreter)?;
+            }
+            Stmt::Print { values, .. } => {
+                // every value is evaluated before anything is written, an error prints nothing
+                let mut texts = Vec::new();
+                for expr in values {
+                    let value = expr.evaluate_in(interpreter)?;
+                    texts.push(interpreter.display(&value));
+                }
+                interpreter.write_line(&texts.join(" "));
+            }
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                let value = match initializer {
+                    Some(initializer) => initializer.evaluate_in(interpreter)?,
+                    None => Value::Nil,
+                };
+                Self::check_not_constant(name, interpreter)?;
+                interpreter.globals.define(name.lexeme.clone(), value);
+            }
+            Stmt::Const {
+                name, initializer, ..
+            } => {
+                let value = initializer.evaluate_in(interpreter)?;
+                Self::check_not_constant(name, interpreter)?;
+                interpreter
+                    .globals
+                    .define_constant(name.lexeme.clone(), value);
+            }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+                ..
+            } => Self::execute_switch(subject, cases, default, interpreter)?,
+            Stmt::ForEach {
+                keyword,
+                name,
+                iterable,
+                body,
+            } => Self::execute_for_each(keyword, name, iterable, body, interpreter)?,
+        }
+        Ok(())
+    }
+
+    /// the bytes of the source this statement was parsed from
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression {
+                expr,
+                semicolon: Some(semicolon),
+            } => expr.span().to(semicolon.span()),
+            Stmt::Expression { expr, .. } => expr.span(),
+            Stmt::Print {
+                keyword, semicolon, ..
+            }
+            | Stmt::Var {
+                keyword, semicolon, ..
+            }
+            | Stmt::Const {
+                keyword, semicolon, ..
+            } => keyword.span().to(semicolon.span()),
+            Stmt::Switch {
+                keyword, closing, ..
+            } => keyword.span().to(closing.span()),
+            Stmt::ForEach { keyword, body, .. } => keyword.span().to(body.span()),
+        }
+    }
+
+    /// redeclaring a constant would be a way around it being read-only
+    fn check_not_constant(name: &Token, interpreter: &Interpreter) -> Result<(), Error> {
+        if interpreter.globals.is_constant(&name.lexeme) {
+            return Err(Error::runtime(
+                format!("Cannot assign to constant '{}'", name.lexeme),
+                Position::new(name.line, name.column, name.offset),
+            ));
+        }
+        Ok(())
+    }
+
+    /// run the body of the first case equal to the subject, there is no fallthrough
+    fn execute_switch(
+        subject: &Expr,
+        cases: &[(Expr, Vec<Stmt>)],
+        default: &Option<Vec<Stmt>>,
+        interpreter: &mut Interpreter,
+    ) -> Result<(), Error> {
+        let subject_val = subject.evaluate_in(interpreter)?;
+
+        for (value, body) in cases {
+            // case values are only evaluated until one matches
+            if value.evaluate_in(interpreter)? == subject_val {
+                return Self::execute_all(body, interpreter);
+            }
+        }
+        match default {
+            Some(body) => Self::execute_all(body, interpreter),
+            None => Ok(()),
+        }
+    }
+
+    /// run the body once per element, there are only globals so the loop variable is bound for
+    /// the loop and whatever it shadowed comes back afterwards
+    fn execute_for_each(
+        keyword: &Token,
+        name: &Token,
+        iterable: &Expr,
+        body: &Stmt,
+        interpreter: &mut Interpreter,
+    ) -> Result<(), Error> {
+        let elements: Box<dyn Iterator<Item = Value>> = match iterable.evaluate_in(interpreter)? {
+            Value::Range(start, end) => Box::new(Value::range_values(start, end)),
+            Value::Bytes(bytes) => {
+                Box::new(bytes.into_iter().map(|byte| Value::Number(byte as f64)))
+            }
+            // the keys, in the order they were inserted
+            Value::Map(map) => Box::new(
+                map.keys()
+                    .map(|key| key.to_value())
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            ),
+            other => {
+                return Err(Error::type_error(
+                    format!(
+                        "Can only iterate over ranges, bytes and maps, found {}",
+                        other.type_name()
+                    ),
+                    Position::new(keyword.line, keyword.column, keyword.offset),
+                ))
+            }
+        };
+        Self::check_not_constant(name, interpreter)?;
+
+        let position = Position::new(keyword.line, keyword.column, keyword.offset);
+        let shadowed = interpreter.globals.remove(&name.lexeme);
+        let mut result = Ok(());
+        for element in elements {
+            // a long loop over a range could otherwise outlive the timeout without making a call
+            result = interpreter.check_deadline(&position);
+            if result.is_err() {
+                break;
+            }
+            interpreter.globals.define(name.lexeme.clone(), element);
+            result = body.execute(interpreter);
+            if result.is_err() {
+                break;
+            }
+        }
+        interpreter.globals.remove(&name.lexeme);
+        if let Some(value) = shadowed {
+            interpreter.globals.define(name.lexeme.clone(), value);
+        }
+        result
+    }
+
+    pub fn execute_all(statements: &[Stmt], interpreter: &mut Interpreter) -> Result<(), Error> {
+        for statement in statements {
+            statement.execute(interpreter)?;
+        }
+        Ok(())
+    }
+}
+
+/// a hash of the program's structure, positions left out, so the same code spaced or commented
+/// differently hashes the same. tools can key cached results on it, it is stable across runs
+/// but may change with the rust version, so don't persist it between builds
+pub fn program_hash(program: &[Stmt]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    program.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::interpreter::lox::Lox;
+    use crate::interpreter::runtime::test_support::{lox, run};
+    use std::time::Duration;
+
+    const SWITCH: &str = "switch (x) { case 1: print \"one\"; case 1 + 1: print \"two\"; \
+                          default: print \"many\"; }";
+
+    #[test]
+    fn statements_run_in_order() {
+        assert_eq!(run("print 1; 2; print 3;").unwrap(), "1\n3\n");
+    }
+
+    #[test]
+    fn print_separates_its_values_with_spaces() {
+        assert_eq!(run("print 1, \"x\", true;").unwrap(), "1 x true\n");
+        // a comma inside parentheses is the comma operator
+        assert_eq!(run("print (1, 2), 3;").unwrap(), "2 3\n");
+        let error = run("print 1,;").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Parse);
+    }
+
+    #[test]
+    fn switch_runs_only_the_matching_case() {
+        let output = run(&format!("var x = 2; {}", SWITCH)).unwrap();
+        assert_eq!(output, "two\n");
+    }
+
+    #[test]
+    fn switch_without_a_match_runs_the_default() {
+        let output = run(&format!("var x = 3; {}", SWITCH)).unwrap();
+        assert_eq!(output, "many\n");
+    }
+
+    #[test]
+    fn switch_without_a_match_or_default_does_nothing() {
+        let output = run("switch (\"x\") { case \"y\": print 1; } print 2;").unwrap();
+        assert_eq!(output, "2\n");
+    }
+
+    #[test]
+    fn var_declares_and_assignment_updates() {
+        let output = run("var a; var b = 1; print a, b; print b = b + 1; print b;").unwrap();
+        assert_eq!(output, "nil 1\n2\n2\n");
+        // a redeclaration replaces the variable
+        assert_eq!(run("var a = 1; var a = \"x\"; print a;").unwrap(), "x\n");
+    }
+
+    #[test]
+    fn modulo_assignment_updates_the_variable() {
+        let output = run("var x = 10; x %= 3; print x, x == 1; print x %= 1;").unwrap();
+        assert_eq!(output, "1 true\n0\n");
+    }
+
+    #[test]
+    fn assigning_an_undeclared_variable_is_an_error() {
+        let error = run("y = 1;").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "Undefined variable 'y'");
+    }
+
+    #[test]
+    fn constants_are_read_only() {
+        assert_eq!(run("const c = 2; print c * 3;").unwrap(), "6\n");
+        for source in ["c = 3;", "c %= 2;", "var c = 3;", "const c = 3;"] {
+            let error = run(&format!("const c = 2; {}", source)).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::Runtime, "{}", source);
+            assert_eq!(error.message, "Cannot assign to constant 'c'");
+        }
+    }
+
+    #[test]
+    fn for_each_binds_each_element_and_restores_the_shadowed_name() {
+        let source = "var i = \"outer\"; for (i in {\"a\": 1, \"b\": 2}) print i; print i;";
+        assert_eq!(run(source).unwrap(), "a\nb\nouter\n");
+        assert_eq!(
+            run("for (byte in b\"AB\") print byte;").unwrap(),
+            "65\n66\n"
+        );
+    }
+
+    #[test]
+    fn for_each_stops_at_the_deadline() {
+        let (mut lox, output) = lox();
+        lox.interpreter.timeout = Some(Duration::ZERO);
+        let error = lox.run("for (i in 0..1000000000) print i;").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "Execution timed out");
+        assert_eq!((error.position.line, error.position.column), (1, 1));
+        assert_eq!(output.contents(), "");
+        // the loop variable is cleaned up on the way out
+        assert!(lox.run("i;").is_err());
+    }
+
+    #[test]
+    fn long_for_each_runs_until_the_timeout() {
+        let (mut lox, _) = lox();
+        lox.interpreter.timeout = Some(Duration::from_millis(50));
+        // the body makes no calls, only the check on each iteration can stop it
+        let error = lox.run("for (x in 0..1000000000000) x;").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "Execution timed out");
+    }
+
+    #[test]
+    fn programs_differing_in_spacing_hash_the_same() {
+        let hash = |source: &str| program_hash(&Lox::new().parse_program(source).unwrap());
+        assert_eq!(hash("1+2"), hash("1 + 2"));
+        assert_eq!(
+            hash("var x=1;print x;"),
+            hash("var x = 1; /* set */\nprint x; // show")
+        );
+        assert_ne!(hash("1 + 2"), hash("2 + 1"));
+        assert_ne!(hash("1 + 2"), hash("1 + 2;"));
+    }
+
+    #[test]
+    fn trace_shows_each_statement_before_its_expressions() {
+        let (mut lox, output) = lox();
+        lox.interpreter.trace = true;
+        lox.interpreter.precision = Some(3);
+        lox.run("var x = 1.23456; print x;").unwrap();
+        let trace = [
+            "(var x 1.23456)",
+            "  1.23456",
+            "  => 1.23",
+            "(print x)",
+            "  x",
+            "  => 1.23",
+            "1.23",
+        ];
+        assert_eq!(output.contents(), trace.join("\n") + "\n");
+    }
+}
@@ -1,27 +1,48 @@
 use crate::error::{Error, ErrorKind, Position};
-use crate::parser::value::Value;
-use crate::token::{Token, TokenKind};
+#[cfg(test)]
+use crate::interpreter::environment::Environment;
+use crate::interpreter::natives::strings;
+use crate::interpreter::runtime::Interpreter;
+use crate::parser::map::Map;
+use crate::parser::precedence::{self, Associativity};
+use crate::parser::value::{format_number, Value};
+use crate::token::{Span, Token, TokenKind};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 
 /*
-   expression = literal | unary | binary | grouping;
+   expression = assignment | literal | unary | binary | grouping;
+   assignment = IDENTIFIER ( "=" | "%=" ) expression;
    literal = NUMBER | STRING | "true" | "false" | "nill";
-   grouping = "(" expression ")";
-   unary = ( "-" | "|" ) expression;
+   grouping = "(" expression ( "," expression )* ")";
+   unary = ( "-" | "+" | "!" | "~" | "typeof" ) expression;
+   call = primary ( "(" arguments? ")" | "[" expression "]" | "." IDENTIFIER )*;
+   primary = ... | "this" | "super" "." IDENTIFIER;
+   map = "{" ( expression ":" expression ( "," expression ":" expression )* ","? )? "}";
+   range = expression ".." expression;
    binary = expression operator expression;
-   operator = "==" | "!=" | "<" | "<=" | ">" | ">=" | "+" | "-" | "*" | "/";
+   operator = "==" | "!=" | "<" | "<=" | ">" | ">=" | "+" | "-" | "*" | "/" | "%";
 */
 
 // Box<Expr> provide known size at compile time
-#[derive(Debug, Clone)]
+// equality is structural, tokens compare without their positions (see `Token`), and so is
+// hashing: code that only differs in spacing or comments hashes the same
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub enum Expr {
-    Literal(Literal),
+    Literal {
+        value: Literal,
+        token: Token, // where the literal was written, used to report errors about it
+    },
     Binary {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
     Grouping {
+        opening: Token, // the parentheses, kept to know which source text the group covers
         expr: Box<Expr>,
+        closing: Token,
     },
     Unary {
         operator: Token,
@@ -29,64 +50,265 @@ pub enum Expr {
     },
     Ternary {
         condition: Box<Expr>,
+        question: Token, // the '?', used to report errors about the condition
         then_expr: Box<Expr>,
         else_expr: Box<Expr>,
     },
+    Variable {
+        name: Token,
+    },
+    Assign {
+        name: Token,
+        value: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token, // closing parenthesis, used to report errors at the call site
+        arguments: Vec<Expr>,
+    },
+    MapLiteral {
+        brace: Token, // opening brace, used to report errors about the keys
+        entries: Vec<(Expr, Expr)>,
+        closing: Token,
+    },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        closing: Token,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Range {
+        start: Box<Expr>,
+        dots: Token, // the '..', used to report errors about the bounds
+        end: Box<Expr>,
+    },
+    This {
+        keyword: Token,
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+    },
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
     Number(f64),
-    String(String),
+    String(Rc<str>), // shared with the values it evaluates to
+    Bytes(Vec<u8>),
     Bool(bool),
     Nil,
 }
 
+/// numbers hash by bit pattern, with -0.0 folded into 0.0 since they compare equal
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Literal::Number(n) if *n == 0.0 => 0f64.to_bits().hash(state),
+            Literal::Number(n) => n.to_bits().hash(state),
+            Literal::String(s) => s.hash(state),
+            Literal::Bytes(bytes) => bytes.hash(state),
+            Literal::Bool(b) => b.hash(state),
+            Literal::Nil => {}
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Notation {
     Lisp,
+    LispCompact, // lisp without the `group` nodes of parentheses the source could leave out
     Rpn,
     Polish,
 }
 
+impl Notation {
+    /// notation selected by name on the command line, e.g. `--notation rpn`
+    pub fn from_name(name: &str) -> Option<Notation> {
+        match name {
+            "lisp" => Some(Notation::Lisp),
+            "lisp-compact" => Some(Notation::LispCompact),
+            "rpn" => Some(Notation::Rpn),
+            "polish" => Some(Notation::Polish),
+            _ => None,
+        }
+    }
+}
+
+/// the `min` the parser reads the left and right operand of an infix operator with, see
+/// `Parser::infix`. the operand on the associative side may bind as loose as the operator
+fn operand_precedences(kind: TokenKind) -> (u8, u8) {
+    let precedence = precedence::precedence(kind).unwrap_or(precedence::CALL);
+    match precedence::associativity(kind) {
+        Some(Associativity::Right) => (precedence + 1, precedence),
+        // comparisons can't be chained, `(a < b) < c` needs its parentheses
+        _ if precedence == precedence::COMPARISON => (precedence + 1, precedence + 1),
+        _ => (precedence, precedence + 1),
+    }
+}
+
 impl Expr {
+    /// evaluate the expression with a new interpreter, only the natives are in scope
+    #[cfg(test)]
     pub fn evaluate(&self) -> Result<Value, Error> {
+        self.evaluate_in(&mut Interpreter::new())
+    }
+
+    /// evaluate the expression with `env` as the globals of a new interpreter, so it can read
+    /// and assign the variables defined there (and only those, natives included)
+    #[cfg(test)]
+    pub fn evaluate_with(&self, env: &mut Environment) -> Result<Value, Error> {
+        let mut interpreter = Interpreter::new();
+        interpreter.globals = std::mem::take(env);
+        let result = self.evaluate_in(&mut interpreter);
+        *env = interpreter.globals; // assignments made while evaluating are kept
+        result
+    }
+
+    pub fn evaluate_in(&self, interpreter: &mut Interpreter) -> Result<Value, Error> {
+        if !interpreter.trace {
+            return self.evaluate_node(interpreter);
+        }
+
+        interpreter.trace_line(&self.print(Notation::Lisp));
+        interpreter.depth += 1;
+        let result = self.evaluate_node(interpreter);
+        interpreter.depth -= 1;
+        if let Ok(value) = &result {
+            let value = interpreter.display(value);
+            interpreter.trace_line(&format!("=> {}", value));
+        }
+        result
+    }
+
+    fn evaluate_node(&self, interpreter: &mut Interpreter) -> Result<Value, Error> {
         match self {
-            Expr::Literal(lit) => Self::evaluate_literal(lit),
-            Expr::Grouping { expr } => expr.evaluate(),
-            Expr::Unary { operator, right } => Self::evaluate_unary(operator, right),
+            Expr::Literal { value, token } => Self::evaluate_literal(value, token, interpreter),
+            Expr::Grouping { expr, .. } => expr.evaluate_in(interpreter),
+            Expr::Unary { operator, right } => Self::evaluate_unary(operator, right, interpreter),
             Expr::Binary {
                 left,
                 operator,
                 right,
-            } => Self::evaluate_binary(left, operator, right),
+            } => Self::evaluate_binary(left, operator, right, interpreter),
             Expr::Ternary {
                 condition,
+                question,
                 then_expr,
                 else_expr,
-            } => Self::evaluate_ternary(condition, then_expr, else_expr),
+            } => Self::evaluate_ternary(condition, question, then_expr, else_expr, interpreter),
+            Expr::Variable { name } => interpreter.globals.get(name),
+            Expr::Assign { name, value } => {
+                let value = value.evaluate_in(interpreter)?;
+                interpreter.globals.assign(name, value.clone())?;
+                Ok(value)
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => Self::evaluate_call(callee, paren, arguments, interpreter),
+            Expr::MapLiteral { brace, entries, .. } => {
+                Self::evaluate_map_literal(brace, entries, interpreter)
+            }
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => Self::evaluate_index(object, bracket, index, interpreter),
+            Expr::Get { object, name } => {
+                // methods are only looked up by calls, see `evaluate_method_call`
+                let object_val = object.evaluate_in(interpreter)?;
+                let position = Position::new(name.line, name.column, name.offset);
+                match object_val {
+                    Value::String(_) if strings::method(&name.lexeme).is_some() => {
+                        Err(Error::runtime(
+                            format!("Method '{}' must be called", name.lexeme),
+                            position,
+                        )
+                        .with_help(format!("Add parentheses: .{}()", name.lexeme)))
+                    }
+                    _ => Err(Self::no_method(&object_val, name)),
+                }
+            }
+            Expr::Range { start, dots, end } => {
+                let position = Position::new(dots.line, dots.column, dots.offset);
+                let start = Self::range_bound(start.evaluate_in(interpreter)?, position.clone())?;
+                let end = Self::range_bound(end.evaluate_in(interpreter)?, position)?;
+                Ok(Value::Range(start, end))
+            }
+            // lox has no classes yet, so there is never an instance or superclass to refer to
+            Expr::This { keyword } | Expr::Super { keyword, .. } => Err(Error::runtime(
+                format!("Can't use '{}' outside of a class", keyword.lexeme),
+                Position::new(keyword.line, keyword.column, keyword.offset),
+            )),
         }
     }
 
-    fn evaluate_literal(lit: &Literal) -> Result<Value, Error> {
+    fn evaluate_literal(
+        lit: &Literal,
+        token: &Token,
+        interpreter: &Interpreter,
+    ) -> Result<Value, Error> {
         Ok(match lit {
+            Literal::Number(n) if !n.is_finite() && !interpreter.ieee_numbers => {
+                return Err(Error::runtime(
+                    "Numeric overflow",
+                    Position::new(token.line, token.column, token.offset),
+                )
+                .with_help("This number literal is too large to be represented"))
+            }
             Literal::Number(n) => Value::Number(f64::from(*n)),
             Literal::String(s) => Value::String(s.clone()),
+            Literal::Bytes(bytes) => Value::Bytes(bytes.clone()),
             Literal::Bool(b) => Value::Bool(*b),
             Literal::Nil => Value::Nil,
         })
     }
 
-    fn evaluate_unary(operator: &Token, right: &Expr) -> Result<Value, Error> {
-        let right_val = right.evaluate()?;
+    fn evaluate_unary(
+        operator: &Token,
+        right: &Expr,
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, Error> {
+        let right_val = right.evaluate_in(interpreter)?;
         match operator.kind {
-            TokenKind::Minus => match right_val {
-                Value::Number(n) => Ok(Value::Number(-n)),
-                _ => Err(Error::runtime(
-                    "Operand must be a number.",
+            TokenKind::Minus => Ok(Value::Number(-Self::number_operand(operator, &right_val)?)),
+            // a no-op on numbers, still checked like '-' so `+"a"` isn't silently accepted
+            TokenKind::Plus => Ok(Value::Number(Self::number_operand(operator, &right_val)?)),
+            // bitwise not of the number as a 64-bit integer, so `~5` is -6
+            TokenKind::Tilde => match right_val {
+                Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+                    Ok(Value::Number(!(n as i64) as f64))
+                }
+                Value::Number(n) => Err(Error::type_error(
+                    format!(
+                        "Operand of '~' must be a 64-bit whole number, found {}",
+                        format_number(n)
+                    ),
+                    Position::new(operator.line, operator.column, operator.offset),
+                )),
+                _ => Err(Error::type_error(
+                    format!(
+                        "Operand of '~' must be a number, found {}",
+                        right_val.type_name()
+                    ),
                     Position::new(operator.line, operator.column, operator.offset),
                 )),
             },
-            TokenKind::Bang => Ok(Value::Bool(!Value::is_truthy(&right_val))),
+            TokenKind::TypeOf => Ok(Value::String(right_val.type_name().into())),
+            TokenKind::Bang => {
+                let truthy = interpreter.is_truthy(
+                    &right_val,
+                    Position::new(operator.line, operator.column, operator.offset),
+                )?;
+                Ok(Value::Bool(!truthy))
+            }
             _ => Err(Error::runtime(
                 "Invalid unary operator.",
                 Position::new(operator.line, operator.column, operator.offset),
@@ -94,124 +316,1046 @@ impl Expr {
         }
     }
 
-    fn evaluate_binary(left: &Expr, operator: &Token, right: &Expr) -> Result<Value, Error> {
-        let left_val = left.evaluate()?;
-        let right_val = right.evaluate()?;
-        match operator.kind {
+    /// the operand of a unary `-` or `+`, which must be a number
+    fn number_operand(operator: &Token, value: &Value) -> Result<f64, Error> {
+        match value {
+            Value::Number(n) => Ok(*n),
+            _ => Err(Error::type_error(
+                format!(
+                    "Operand of '{}' must be a number, found {}",
+                    operator.lexeme,
+                    value.type_name()
+                ),
+                Position::new(operator.line, operator.column, operator.offset),
+            )
+            .with_help(format!(
+                "Unary '{}' only applies to numbers",
+                operator.lexeme
+            ))),
+        }
+    }
+
+    fn evaluate_binary(
+        left: &Expr,
+        operator: &Token,
+        right: &Expr,
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, Error> {
+        if operator.kind == TokenKind::Comma {
+            // the left value is dropped, it only runs for its side effects (and errors)
+            left.evaluate_in(interpreter)?;
+            return right.evaluate_in(interpreter);
+        }
+        let left_val = left.evaluate_in(interpreter)?;
+        let right_val = right.evaluate_in(interpreter)?;
+        let result = match operator.kind {
             TokenKind::Plus => match (&left_val, &right_val) {
                 (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
-                (Value::String(a), Value::String(b)) => Ok(Value::String(a.clone() + b)),
-                _ => Err(Error::runtime(
-                    "Operands must be two numbers or two strings",
+                (Value::String(a), Value::String(b)) => {
+                    Ok(Value::String(format!("{}{}", a, b).into()))
+                }
+                // in coerce mode the other operand is converted with its display form
+                (Value::String(a), b) if interpreter.coerce_strings => {
+                    Ok(Value::String(format!("{}{}", a, b).into()))
+                }
+                (a, Value::String(b)) if interpreter.coerce_strings => {
+                    Ok(Value::String(format!("{}{}", a, b).into()))
+                }
+                (a, b) => Err(Error::invalid_operand_types(
+                    &operator.lexeme,
+                    a.type_name(),
+                    b.type_name(),
                     Position::new(operator.line, operator.column, operator.offset),
-                )),
+                )
+                .with_help("The + operator adds two numbers or joins two strings")),
             },
             TokenKind::Minus => Value::binary_number_operation(
                 &left_val,
                 &right_val,
+                &operator.lexeme,
                 |a, b| a - b,
                 Position::new(operator.line, operator.column, operator.offset),
             ),
             TokenKind::Star => Value::binary_number_operation(
                 &left_val,
                 &right_val,
-                |a, b| a + b,
+                &operator.lexeme,
+                |a, b| a * b,
+                Position::new(operator.line, operator.column, operator.offset),
+            ),
+            TokenKind::Slash | TokenKind::Percent
+                if !interpreter.ieee_numbers
+                    && matches!(left_val, Value::Number(_))
+                    && right_val == Value::Number(0.0) =>
+            {
+                Err(Error::division_by_zero(Position::new(
+                    operator.line,
+                    operator.column,
+                    operator.offset,
+                )))
+            }
+            TokenKind::Slash => {
+                let integer_division = interpreter.integer_division;
+                Value::binary_number_operation(
+                    &left_val,
+                    &right_val,
+                    &operator.lexeme,
+                    |a, b| match integer_division && a.fract() == 0.0 && b.fract() == 0.0 {
+                        true => (a / b).trunc(), // rounded toward zero, `-7 / 2` is -3
+                        false => a / b,
+                    },
+                    Position::new(operator.line, operator.column, operator.offset),
+                )
+            }
+            // the remainder takes the sign of the dividend, `-7 % 3` is -1
+            TokenKind::Percent => Value::binary_number_operation(
+                &left_val,
+                &right_val,
+                &operator.lexeme,
+                |a, b| a % b,
                 Position::new(operator.line, operator.column, operator.offset),
             ),
-            TokenKind::Slash => Value::binary_number_operation(
+            TokenKind::Greater => Value::compare(
                 &left_val,
                 &right_val,
-                |a, b| a / b,
+                &operator.lexeme,
+                |o| o == Some(Ordering::Greater),
                 Position::new(operator.line, operator.column, operator.offset),
             ),
+            TokenKind::GreaterEqual => Value::compare(
+                &left_val,
+                &right_val,
+                &operator.lexeme,
+                |o| matches!(o, Some(Ordering::Greater | Ordering::Equal)),
+                Position::new(operator.line, operator.column, operator.offset),
+            ),
+            TokenKind::Less => Value::compare(
+                &left_val,
+                &right_val,
+                &operator.lexeme,
+                |o| o == Some(Ordering::Less),
+                Position::new(operator.line, operator.column, operator.offset),
+            ),
+            TokenKind::LessEqual => Value::compare(
+                &left_val,
+                &right_val,
+                &operator.lexeme,
+                |o| matches!(o, Some(Ordering::Less | Ordering::Equal)),
+                Position::new(operator.line, operator.column, operator.offset),
+            ),
+            TokenKind::EqualEqual => Ok(Value::Bool(left_val == right_val)),
+            TokenKind::BangEqual => Ok(Value::Bool(left_val != right_val)),
             _ => Err(Error::runtime(
                 "Invalid binary operator",
                 Position::new(operator.line, operator.column, operator.offset),
             )),
+        }?;
+
+        // finite operands producing inf/NaN means the operation overflowed
+        if let (Value::Number(result), Value::Number(a), Value::Number(b)) =
+            (&result, &left_val, &right_val)
+        {
+            if !result.is_finite() && a.is_finite() && b.is_finite() && !interpreter.ieee_numbers {
+                return Err(Error::runtime(
+                    "Numeric overflow",
+                    Position::new(operator.line, operator.column, operator.offset),
+                )
+                .with_help("The result is too large to be represented"));
+            }
         }
+        Ok(result)
     }
 
     fn evaluate_ternary(
         condition: &Expr,
+        question: &Token,
         then_expr: &Expr,
         else_expr: &Expr,
+        interpreter: &mut Interpreter,
     ) -> Result<Value, Error> {
-        let condition_val = condition.evaluate()?;
+        let condition_val = condition.evaluate_in(interpreter)?;
+        let position = Position::new(question.line, question.column, question.offset);
 
-        if Value::is_truthy(&condition_val) {
-            Ok(then_expr.evaluate()?)
+        if interpreter.is_truthy(&condition_val, position)? {
+            Ok(then_expr.evaluate_in(interpreter)?)
         } else {
-            Ok(else_expr.evaluate()?)
+            Ok(else_expr.evaluate_in(interpreter)?)
+        }
+    }
+
+    fn evaluate_call(
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, Error> {
+        if let Expr::Get { object, name } = callee {
+            return Self::evaluate_method_call(object, name, paren, arguments, interpreter);
+        }
+        let callee_val = callee.evaluate_in(interpreter)?;
+
+        let mut argument_vals = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            argument_vals.push(argument.evaluate_in(interpreter)?);
+        }
+
+        let position = Position::new(paren.line, paren.column, paren.offset);
+        interpreter.check_deadline(&position)?;
+        match callee_val {
+            Value::Native(native) => {
+                if !native.arity.accepts(argument_vals.len()) {
+                    return Err(Error::runtime(
+                        format!(
+                            "Expected {} arguments but got {}",
+                            native.arity,
+                            argument_vals.len()
+                        ),
+                        position,
+                    ));
+                }
+                (native.function)(interpreter, &argument_vals, &position)
+            }
+            _ => Err(Error::type_error(
+                format!("Can only call functions, found {}", callee_val.type_name()),
+                position,
+            )),
+        }
+    }
+
+    /// `receiver.name(arguments)`, only strings have methods for now
+    fn evaluate_method_call(
+        object: &Expr,
+        name: &Token,
+        paren: &Token,
+        arguments: &[Expr],
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, Error> {
+        let receiver = object.evaluate_in(interpreter)?;
+        let method = match &receiver {
+            Value::String(_) => strings::method(&name.lexeme),
+            _ => None,
+        };
+        let Some(method) = method else {
+            return Err(Self::no_method(&receiver, name));
+        };
+
+        let mut argument_vals = Vec::with_capacity(arguments.len() + 1);
+        argument_vals.push(receiver);
+        for argument in arguments {
+            argument_vals.push(argument.evaluate_in(interpreter)?);
+        }
+
+        let position = Position::new(paren.line, paren.column, paren.offset);
+        interpreter.check_deadline(&position)?;
+        if !method.arity.accepts(arguments.len()) {
+            return Err(Error::runtime(
+                format!(
+                    "Expected {} arguments but got {}",
+                    method.arity,
+                    arguments.len()
+                ),
+                position,
+            ));
+        }
+        (method.function)(interpreter, &argument_vals, &position)
+    }
+
+    fn no_method(receiver: &Value, name: &Token) -> Error {
+        let position = Position::new(name.line, name.column, name.offset);
+        match receiver {
+            Value::String(_) => Error::runtime(
+                format!("Undefined method '{}' for string", name.lexeme),
+                position,
+            ),
+            _ => Error::type_error(
+                format!("Only strings have methods, found {}", receiver.type_name()),
+                position,
+            ),
         }
     }
 
+    fn evaluate_map_literal(
+        brace: &Token,
+        entries: &[(Expr, Expr)],
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, Error> {
+        let position = Position::new(brace.line, brace.column, brace.offset);
+        let mut map = Map::new();
+
+        for (key, value) in entries {
+            let key = key.evaluate_in(interpreter)?.to_key(position.clone())?;
+            let value = value.evaluate_in(interpreter)?;
+            map.insert(key, value); // a repeated key keeps the last value
+        }
+        Ok(Value::Map(map))
+    }
+
+    fn evaluate_index(
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        interpreter: &mut Interpreter,
+    ) -> Result<Value, Error> {
+        let object_val = object.evaluate_in(interpreter)?;
+        let index_val = index.evaluate_in(interpreter)?;
+        let position = Position::new(bracket.line, bracket.column, bracket.offset);
+
+        let map = match &object_val {
+            Value::Map(map) => map,
+            Value::Bytes(bytes) => return Self::index_bytes(bytes, &index_val, position),
+            Value::Range(start, end) => {
+                return Self::index_range(*start, *end, &index_val, position)
+            }
+            _ => {
+                return Err(Error::type_error(
+                    format!(
+                        "Can only index maps, bytes and ranges, found {}",
+                        object_val.type_name()
+                    ),
+                    position,
+                ))
+            }
+        };
+        match map.get(&index_val.to_key(position.clone())?) {
+            Some(value) => Ok(value.clone()),
+            None if interpreter.strict_keys => Err(Error::runtime(
+                format!("Key {} not found in map", index_val.repr()),
+                position,
+            )),
+            None => Ok(Value::Nil),
+        }
+    }
+
+    /// bounds of a range must be whole numbers
+    fn range_bound(value: Value, position: Position) -> Result<i64, Error> {
+        match value {
+            Value::Number(n) if n.fract() == 0.0 => Ok(n as i64),
+            Value::Number(n) => Err(Error::type_error(
+                format!(
+                    "Range bounds must be whole numbers, found {}",
+                    format_number(n)
+                ),
+                position,
+            )),
+            other => Err(Error::type_error(
+                format!("Range bounds must be numbers, found {}", other.type_name()),
+                position,
+            )),
+        }
+    }
+
+    /// the byte at a whole, in range index, as a number
+    fn index_bytes(bytes: &[u8], index: &Value, position: Position) -> Result<Value, Error> {
+        let Value::Number(n) = index else {
+            return Err(Error::type_error(
+                format!("Bytes index must be a number, found {}", index.type_name()),
+                position,
+            ));
+        };
+        if n.fract() != 0.0 || *n < 0.0 || *n >= bytes.len() as f64 {
+            return Err(Error::runtime(
+                format!(
+                    "Index {} out of range for bytes of length {}",
+                    format_number(*n),
+                    bytes.len()
+                ),
+                position,
+            )
+            .with_help("Indexes are whole numbers from 0 to len(bytes) - 1"));
+        }
+        Ok(Value::Number(bytes[*n as usize] as f64))
+    }
+
+    /// the number `index` steps past the start, `(1..5)[1]` is 2
+    fn index_range(
+        start: i64,
+        end: i64,
+        index: &Value,
+        position: Position,
+    ) -> Result<Value, Error> {
+        let len = Value::range_len(start, end);
+        let Value::Number(n) = index else {
+            return Err(Error::type_error(
+                format!("Range index must be a number, found {}", index.type_name()),
+                position,
+            ));
+        };
+        if n.fract() != 0.0 || *n < 0.0 || *n >= len as f64 {
+            return Err(Error::runtime(
+                format!(
+                    "Index {} out of range for range of length {}",
+                    format_number(*n),
+                    len
+                ),
+                position,
+            )
+            .with_help("Indexes are whole numbers from 0 to len(range) - 1"));
+        }
+        Ok(Value::Number((start + *n as i64) as f64))
+    }
+
     pub fn print(&self, notation: Notation) -> String {
+        // a whole expression parses like a printed value or an argument, at assignment level
+        self.print_operand(notation, precedence::ASSIGNMENT)
+    }
+
+    /// print an operand the parser reads with `parse_precedence(min)`. lisp-compact leaves out
+    /// a group whose expression binds at least that tight, so it parses the same without the
+    /// parentheses, and keeps it otherwise: `(1, 2)`, `(1 + 2) * 3`
+    fn print_operand(&self, notation: Notation, min: u8) -> String {
+        match (self, notation) {
+            (Expr::Grouping { expr, .. }, Notation::LispCompact) if expr.precedence() >= min => {
+                expr.print_operand(notation, min)
+            }
+            _ => self.print_node(notation),
+        }
+    }
+
+    fn print_node(&self, notation: Notation) -> String {
         match self {
-            Expr::Literal(lit) => match lit {
-                Literal::Number(n) => n.to_string(),
+            Expr::Literal { value, .. } => match value {
+                Literal::Number(n) => format_number(*n),
                 Literal::String(s) => format!("\"{}\"", s),
+                Literal::Bytes(bytes) => Value::Bytes(bytes.clone()).to_string(),
                 Literal::Bool(b) => b.to_string(),
                 Literal::Nil => "nil".into(),
             },
 
-            Expr::Grouping { expr } => match notation {
-                Notation::Lisp => format!("(group {})", expr.print(notation)),
+            Expr::Grouping { expr, .. } => match notation {
+                // inside parentheses the precedence starts over, down to the comma
+                Notation::Lisp | Notation::LispCompact => format!(
+                    "(group {})",
+                    expr.print_operand(notation, precedence::COMMA)
+                ),
+                // parenthesis-free on purpose, the order of the operators already carries the
+                // grouping: `(1 + 2) * 3` is `1 2 + 3 *` while `1 + 2 * 3` is `1 2 3 * +`
                 _ => expr.print(notation),
             },
 
-            Expr::Unary { operator, right } => match notation {
-                Notation::Rpn => format!("{} {}", right.print(notation), operator.lexeme),
-                _ => format!("({} {})", operator.lexeme, right.print(notation)),
-            },
+            Expr::Unary { operator, right } => {
+                let right = right.print_operand(notation, precedence::UNARY);
+                match notation {
+                    Notation::Rpn => format!("{} {}", right, operator.lexeme),
+                    _ => format!("({} {})", operator.lexeme, right),
+                }
+            }
 
             Expr::Binary {
                 left,
                 operator,
                 right,
-            } => match notation {
-                Notation::Lisp => format!(
-                    "({} {} {})",
-                    operator.lexeme,
-                    left.print(notation),
-                    right.print(notation)
-                ),
-                Notation::Polish => format!(
-                    "{} {} {}",
-                    operator.lexeme,
-                    left.print(notation),
-                    right.print(notation)
-                ),
-                Notation::Rpn => format!(
-                    "{} {} {}",
-                    left.print(notation),
-                    right.print(notation),
-                    operator.lexeme
-                ),
-            },
+            } => {
+                let (left_min, right_min) = operand_precedences(operator.kind);
+                let left = left.print_operand(notation, left_min);
+                let right = right.print_operand(notation, right_min);
+                match notation {
+                    Notation::Lisp | Notation::LispCompact => {
+                        format!("({} {} {})", operator.lexeme, left, right)
+                    }
+                    Notation::Polish => format!("{} {} {}", operator.lexeme, left, right),
+                    Notation::Rpn => format!("{} {} {}", left, right, operator.lexeme),
+                }
+            }
             Expr::Ternary {
                 condition,
+                question,
                 then_expr,
                 else_expr,
-            } => match notation {
-                Notation::Lisp => format!(
-                    "(?: {} {} {})",
-                    condition.print(notation),
-                    then_expr.print(notation),
-                    else_expr.print(notation)
-                ),
-                Notation::Polish => format!(
-                    "?: {} {} {}",
-                    condition.print(notation),
-                    then_expr.print(notation),
-                    else_expr.print(notation)
-                ),
-                Notation::Rpn => format!(
-                    "{} {} {} ?:",
-                    condition.print(notation),
-                    then_expr.print(notation),
-                    else_expr.print(notation)
-                ),
+            } => {
+                let (condition_min, else_min) = operand_precedences(question.kind);
+                let condition = condition.print_operand(notation, condition_min);
+                let then_expr = then_expr.print(notation);
+                let else_expr = else_expr.print_operand(notation, else_min);
+                match notation {
+                    Notation::Lisp | Notation::LispCompact => {
+                        format!("(?: {} {} {})", condition, then_expr, else_expr)
+                    }
+                    Notation::Polish => format!("?: {} {} {}", condition, then_expr, else_expr),
+                    Notation::Rpn => format!("{} {} {} ?:", condition, then_expr, else_expr),
+                }
+            }
+            Expr::Variable { name } => name.lexeme.clone(),
+            Expr::Assign { name, value } => match notation {
+                Notation::Lisp | Notation::LispCompact => {
+                    format!("(= {} {})", name.lexeme, value.print(notation))
+                }
+                Notation::Polish => format!("= {} {}", name.lexeme, value.print(notation)),
+                Notation::Rpn => format!("{} {} =", name.lexeme, value.print(notation)),
+            },
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let mut parts = vec![callee.print_operand(notation, precedence::CALL)];
+                parts.extend(arguments.iter().map(|argument| argument.print(notation)));
+                match notation {
+                    Notation::Lisp | Notation::LispCompact => format!("(call {})", parts.join(" ")),
+                    Notation::Polish => format!("call {}", parts.join(" ")),
+                    Notation::Rpn => {
+                        parts.rotate_left(1); // the callee goes after its arguments
+                        format!("{} call", parts.join(" "))
+                    }
+                }
+            }
+            Expr::MapLiteral { entries, .. } => {
+                let mut parts = vec![];
+                for (key, value) in entries {
+                    parts.push(key.print(notation));
+                    parts.push(value.print(notation));
+                }
+                match notation {
+                    Notation::Lisp | Notation::LispCompact if parts.is_empty() => "(map)".into(),
+                    Notation::Lisp | Notation::LispCompact => format!("(map {})", parts.join(" ")),
+                    Notation::Polish => format!("map {}", parts.join(" ")).trim_end().into(),
+                    Notation::Rpn => format!("{} map", parts.join(" ")).trim_start().into(),
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                let object = object.print_operand(notation, precedence::CALL);
+                let index = index.print(notation);
+                match notation {
+                    Notation::Lisp | Notation::LispCompact => {
+                        format!("(index {} {})", object, index)
+                    }
+                    Notation::Polish => format!("index {} {}", object, index),
+                    Notation::Rpn => format!("{} {} index", object, index),
+                }
+            }
+            Expr::Get { object, name } => {
+                let object = object.print_operand(notation, precedence::CALL);
+                match notation {
+                    Notation::Lisp | Notation::LispCompact => {
+                        format!("(. {} {})", object, name.lexeme)
+                    }
+                    Notation::Polish => format!(". {} {}", object, name.lexeme),
+                    Notation::Rpn => format!("{} {} .", object, name.lexeme),
+                }
+            }
+            Expr::Range { start, dots, end } => {
+                let (start_min, end_min) = operand_precedences(dots.kind);
+                let start = start.print_operand(notation, start_min);
+                let end = end.print_operand(notation, end_min);
+                match notation {
+                    Notation::Lisp | Notation::LispCompact => format!("(.. {} {})", start, end),
+                    Notation::Polish => format!(".. {} {}", start, end),
+                    Notation::Rpn => format!("{} {} ..", start, end),
+                }
+            }
+            Expr::This { keyword } => keyword.lexeme.clone(),
+            Expr::Super { method, .. } => match notation {
+                Notation::Lisp | Notation::LispCompact => format!("(. super {})", method.lexeme),
+                Notation::Polish => format!(". super {}", method.lexeme),
+                Notation::Rpn => format!("super {} .", method.lexeme),
             },
         }
     }
+
+    /// render the tree one node per line with `├─`/`└─` connectors, like `cargo tree`
+    pub fn print_tree(&self) -> String {
+        let mut lines = Vec::new();
+        self.tree_lines("", "", &mut lines);
+        lines.join("\n")
+    }
+
+    fn tree_lines(&self, prefix: &str, child_prefix: &str, lines: &mut Vec<String>) {
+        let label = match self {
+            Expr::Literal { .. } => format!("Literal {}", self.print(Notation::Lisp)),
+            Expr::Grouping { .. } => "Grouping".into(),
+            Expr::Unary { operator, .. } => format!("Unary {}", operator.lexeme),
+            Expr::Binary { operator, .. } => format!("Binary {}", operator.lexeme),
+            Expr::Ternary { .. } => "Ternary ?:".into(),
+            Expr::Variable { name } => format!("Variable {}", name.lexeme),
+            Expr::Assign { name, .. } => format!("Assign {}", name.lexeme),
+            Expr::Call { .. } => "Call".into(),
+            Expr::MapLiteral { .. } => "Map".into(),
+            Expr::Index { .. } => "Index".into(),
+            Expr::Get { name, .. } => format!("Get {}", name.lexeme),
+            Expr::Range { .. } => "Range ..".into(),
+            Expr::This { .. } => "This".into(),
+            Expr::Super { method, .. } => format!("Super {}", method.lexeme),
+        };
+        lines.push(format!("{}{}", prefix, label));
+
+        let children = self.children();
+        for (i, child) in children.iter().enumerate() {
+            if i + 1 == children.len() {
+                child.tree_lines(
+                    &format!("{}└─ ", child_prefix),
+                    &format!("{}   ", child_prefix),
+                    lines,
+                );
+            } else {
+                child.tree_lines(
+                    &format!("{}├─ ", child_prefix),
+                    &format!("{}│  ", child_prefix),
+                    lines,
+                );
+            }
+        }
+    }
+
+    /// the direct subexpressions in source order, map entries as key then value
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Literal { .. }
+            | Expr::Variable { .. }
+            | Expr::This { .. }
+            | Expr::Super { .. } => vec![],
+            Expr::Grouping { expr, .. } => vec![expr],
+            Expr::Unary { right, .. } => vec![right],
+            Expr::Assign { value, .. } => vec![value],
+            Expr::Get { object, .. } => vec![object],
+            Expr::Binary { left, right, .. } => vec![left, right],
+            Expr::Index { object, index, .. } => vec![object, index],
+            Expr::Range { start, end, .. } => vec![start, end],
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => vec![condition, then_expr, else_expr],
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let mut children = vec![callee.as_ref()];
+                children.extend(arguments);
+                children
+            }
+            Expr::MapLiteral { entries, .. } => entries
+                .iter()
+                .flat_map(|(key, value)| [key, value])
+                .collect(),
+        }
+    }
+
+    /// how tightly the outermost operator binds, as in `precedence.rs`. nothing pulls apart a
+    /// primary, a group or a postfix chain, so they bind tightest
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary { operator, .. } => {
+                precedence::precedence(operator.kind).unwrap_or(precedence::CALL)
+            }
+            Expr::Unary { .. } => precedence::UNARY,
+            Expr::Ternary { .. } => precedence::TERNARY,
+            Expr::Assign { .. } => precedence::ASSIGNMENT,
+            Expr::Range { .. } => precedence::RANGE,
+            _ => u8::MAX,
+        }
+    }
+
+    /// how many nodes the tree has, this one included
+    #[cfg(test)]
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(Expr::node_count)
+            .sum::<usize>()
+    }
+
+    /// nodes on the longest path from this one down to a leaf, a literal has depth 1
+    #[cfg(test)]
+    pub fn depth(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(Expr::depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// the bytes of the source this node was parsed from
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Literal { token, .. } => token.span(),
+            Expr::Variable { name } => name.span(),
+            Expr::Grouping {
+                opening, closing, ..
+            } => opening.span().to(closing.span()),
+            Expr::Unary { operator, right } => operator.span().to(right.span()),
+            Expr::Binary { left, right, .. } => left.span().to(right.span()),
+            Expr::Ternary {
+                condition,
+                else_expr,
+                ..
+            } => condition.span().to(else_expr.span()),
+            Expr::Assign { name, value } => name.span().to(value.span()),
+            Expr::Call { callee, paren, .. } => callee.span().to(paren.span()),
+            Expr::MapLiteral { brace, closing, .. } => brace.span().to(closing.span()),
+            Expr::Index {
+                object, closing, ..
+            } => object.span().to(closing.span()),
+            Expr::Get { object, name } => object.span().to(name.span()),
+            Expr::Range { start, end, .. } => start.span().to(end.span()),
+            Expr::This { keyword } => keyword.span(),
+            Expr::Super { keyword, method } => keyword.span().to(method.span()),
+        }
+    }
+
+    /// the text of `source` this node was parsed from, e.g. to highlight it in an editor
+    pub fn source_text<'a>(&self, source: &'a str) -> &'a str {
+        self.span().slice(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::lox::Lox;
+    use crate::interpreter::runtime::test_support;
+    use crate::interpreter::runtime::test_support::run;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn parse(source: &str) -> Expr {
+        Lox::new().parse(source).unwrap()
+    }
+
+    #[test]
+    fn notations_render_the_same_expression_differently() {
+        let expr = parse("1 + 2 * 3");
+        assert_eq!(expr.print(Notation::Lisp), "(+ 1 (* 2 3))");
+        assert_eq!(expr.print(Notation::Rpn), "1 2 3 * +");
+        assert_eq!(expr.print(Notation::Polish), "+ 1 * 2 3");
+    }
+
+    #[test]
+    fn grouping_shows_in_the_operator_order_of_rpn_and_polish() {
+        let grouped = parse("(1 + 2) * 3");
+        assert_eq!(grouped.print(Notation::Rpn), "1 2 + 3 *");
+        assert_eq!(grouped.print(Notation::Polish), "* + 1 2 3");
+        let ungrouped = parse("1 + 2 * 3");
+        assert_ne!(ungrouped.print(Notation::Rpn), grouped.print(Notation::Rpn));
+        assert_ne!(
+            ungrouped.print(Notation::Polish),
+            grouped.print(Notation::Polish)
+        );
+    }
+
+    #[test]
+    fn node_count_and_depth_measure_the_tree() {
+        // (* (- (group (+ 1 2))) (call f 3 x))
+        let expr = parse("-(1 + 2) * f(3, x)");
+        assert_eq!(expr.node_count(), 10);
+        assert_eq!(expr.depth(), 5);
+
+        let literal = parse("1");
+        assert_eq!((literal.node_count(), literal.depth()), (1, 1));
+    }
+
+    #[test]
+    fn lisp_compact_leaves_out_only_redundant_groups() {
+        let expr = parse("(1 + 2)");
+        assert_eq!(expr.print(Notation::Lisp), "(group (+ 1 2))");
+        assert_eq!(expr.print(Notation::LispCompact), "(+ 1 2)");
+
+        // groups that change how the source parses stay
+        let expr = parse("(1 + 2) * (3 * 4)");
+        assert_eq!(
+            expr.print(Notation::LispCompact),
+            "(* (group (+ 1 2)) (group (* 3 4)))"
+        );
+        let expr = parse("((1 * 2)) * 3");
+        assert_eq!(expr.print(Notation::LispCompact), "(* (* 1 2) 3)");
+        assert_eq!(parse("{}").print(Notation::LispCompact), "(map)");
+    }
+
+    #[test]
+    fn evaluate_with_reads_and_assigns_the_environment() {
+        let mut env = Environment::new();
+        env.define("x", Value::Number(41.0));
+        assert_eq!(
+            parse("x + 1").evaluate_with(&mut env).unwrap(),
+            Value::Number(42.0)
+        );
+
+        parse("x = x * 2").evaluate_with(&mut env).unwrap();
+        assert_eq!(
+            parse("x").evaluate_with(&mut env).unwrap(),
+            Value::Number(82.0)
+        );
+
+        // without one only the natives are in scope
+        assert_eq!(parse("abs(-2)").evaluate().unwrap(), Value::Number(2.0));
+        let error = parse("x + 1").evaluate().unwrap_err();
+        assert_eq!(error.message, "Undefined variable 'x'");
+    }
+
+    #[test]
+    fn notations_are_selected_by_name() {
+        assert!(matches!(Notation::from_name("lisp"), Some(Notation::Lisp)));
+        assert!(matches!(Notation::from_name("rpn"), Some(Notation::Rpn)));
+        assert!(matches!(
+            Notation::from_name("polish"),
+            Some(Notation::Polish)
+        ));
+        assert!(Notation::from_name("infix").is_none());
+    }
+
+    #[test]
+    fn tree_shows_the_nesting_of_the_operators() {
+        let tree = parse("1 + 2 * (3 - x)").print_tree();
+        let expected = [
+            "Binary +",
+            "├─ Literal 1",
+            "└─ Binary *",
+            "   ├─ Literal 2",
+            "   └─ Grouping",
+            "      └─ Binary -",
+            "         ├─ Literal 3",
+            "         └─ Variable x",
+        ];
+        assert_eq!(tree, expected.join("\n"));
+    }
+
+    #[test]
+    fn nodes_map_back_to_their_source_text() {
+        let source = "(1 + 2)";
+        assert_eq!(parse(source).source_text(source), "(1 + 2)");
+
+        let source = "  \"é\" + (1 +\n 2) * x  ";
+        let Expr::Binary { right, .. } = parse(source) else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(right.source_text(source), "(1 +\n 2) * x");
+        assert_eq!(right.children()[0].source_text(source), "(1 +\n 2)");
+
+        let source = "var a = 1;\nprint a ,  2 ;";
+        let program = Lox::new().parse_program(source).unwrap();
+        assert_eq!(program[1].span().slice(source), "print a ,  2 ;");
+    }
+
+    #[test]
+    fn literal_errors_point_at_the_literal() {
+        let error = run("1;\nprint 2 + 1e400;").unwrap_err();
+        assert_eq!(error.message, "Numeric overflow");
+        assert_eq!((error.position.line, error.position.column), (2, 11));
+    }
+
+    #[test]
+    fn star_multiplies() {
+        assert_eq!(run("2 * 3").unwrap(), "6\n");
+        assert_eq!(run("-2 * 0.5").unwrap(), "-1\n");
+    }
+
+    #[test]
+    fn overflow_is_an_error_unless_numbers_are_ieee() {
+        assert_eq!(run("1e400").unwrap_err().message, "Numeric overflow");
+        assert_eq!(run("1e308 * 10").unwrap_err().message, "Numeric overflow");
+        assert_eq!(run("1 / 0").unwrap_err().message, "Division by zero");
+
+        let (mut lox, output) = test_support::lox();
+        lox.interpreter.ieee_numbers = true;
+        lox.run("print 1e400; print 1e308 * 10; print -1 / 0;")
+            .unwrap();
+        assert_eq!(output.contents(), "inf\ninf\n-inf\n");
+    }
+
+    #[test]
+    fn integer_division_truncates_only_whole_operands() {
+        assert_eq!(run("print 7 / 2, 6 / 2, -7 / 2;").unwrap(), "3.5 3 -3.5\n");
+
+        let (mut lox, output) = test_support::lox();
+        lox.interpreter.integer_division = true;
+        lox.run("print 7 / 2, 6 / 2, -7 / 2, 7.5 / 2;").unwrap();
+        assert_eq!(output.contents(), "3 3 -3 3.75\n");
+    }
+
+    #[test]
+    fn operand_type_errors_name_the_operator_and_both_types_in_order() {
+        for (source, message) in [
+            (
+                "1 + true",
+                "Invalid operand types for +: number and boolean",
+            ),
+            (
+                "true - 1",
+                "Invalid operand types for -: boolean and number",
+            ),
+            ("\"a\" * nil", "Invalid operand types for *: string and nil"),
+            ("{} % 2", "Invalid operand types for %: map and number"),
+        ] {
+            let error = run(source).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::Runtime, "{}", source);
+            assert_eq!(error.message, message);
+        }
+        // reported at the operator
+        let error = run("1 + true").unwrap_err();
+        assert_eq!((error.position.line, error.position.column), (1, 3));
+    }
+
+    #[test]
+    fn string_coercion_is_opt_in() {
+        let error = run("\"x\" + 1").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Invalid operand types for +: string and number"
+        );
+
+        let (mut lox, output) = test_support::lox();
+        lox.interpreter.coerce_strings = true;
+        lox.run("print \"x\" + 1; print 1 + \"x\"; print \"count: \" + true;")
+            .unwrap();
+        assert_eq!(output.contents(), "x1\n1x\ncount: true\n");
+    }
+
+    #[test]
+    fn strings_compare_lexicographically() {
+        assert_eq!(run("\"apple\" < \"banana\"").unwrap(), "true\n");
+        assert_eq!(run("\"b\" > \"a\"").unwrap(), "true\n");
+        assert_eq!(run("\"a\" >= \"a\"").unwrap(), "true\n");
+        assert_eq!(run("2 <= 1").unwrap(), "false\n");
+
+        let error = run("\"a\" < 1").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(
+            error.message,
+            "Invalid operand types for <: string and number"
+        );
+    }
+
+    #[test]
+    fn calls_check_the_argument_count() {
+        let error = run("sqrt()").unwrap_err();
+        assert_eq!(error.message, "Expected 1 arguments but got 0");
+        assert_eq!(error.position.column, 6);
+        let error = run("pow(1, 2, 3)").unwrap_err();
+        assert_eq!(error.message, "Expected 2 arguments but got 3");
+    }
+
+    #[test]
+    fn trace_shows_each_node_then_its_value() {
+        let (mut lox, output) = test_support::lox();
+        lox.interpreter.trace = true;
+        lox.run("1 + 2").unwrap();
+        let trace = ["(+ 1 2)", "  1", "  => 1", "  2", "  => 2", "=> 3", "3"];
+        assert_eq!(output.contents(), trace.join("\n") + "\n");
+    }
+
+    #[test]
+    fn unary_plus_keeps_numbers_and_rejects_the_rest() {
+        assert_eq!(run("print +5; print +(-3);").unwrap(), "5\n-3\n");
+        let error = run("+\"a\";").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(
+            error.message,
+            "Operand of '+' must be a number, found string"
+        );
+    }
+
+    #[test]
+    fn negating_a_non_number_names_its_type() {
+        let error = run("-\"a\";").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(
+            error.message,
+            "Operand of '-' must be a number, found string"
+        );
+        assert_eq!(
+            error.help.as_deref(),
+            Some("Unary '-' only applies to numbers")
+        );
+        assert_eq!((error.position.line, error.position.column), (1, 1));
+    }
+
+    #[test]
+    fn parenthesized_commas_give_the_last_value() {
+        assert_eq!(run("print (1, 2, 3);").unwrap(), "3\n");
+        // every operand is still evaluated, in order
+        let output = run("var a = 0; print (a = 1, a = a + 1, a * 10); print a;").unwrap();
+        assert_eq!(output, "20\n2\n");
+    }
+
+    #[test]
+    fn comma_runs_its_left_side_and_keeps_the_right() {
+        assert_eq!(run("print (println(\"left\"), 42);").unwrap(), "left\n42\n");
+
+        let error = run("print (undefined, 42);").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "Undefined variable 'undefined'");
+    }
+
+    #[test]
+    fn ranges_count_up_to_their_end() {
+        let output = run("var r = 1..5; print r, len(r), r[0], r[3];").unwrap();
+        assert_eq!(output, "1..5 4 1 4\n");
+        assert_eq!(run("for (i in 1..5) print i;").unwrap(), "1\n2\n3\n4\n");
+        assert_eq!(run("for (i in 5..1) print i;").unwrap(), "");
+        assert_eq!(run("print len(5..1), len(2..2);").unwrap(), "0 0\n");
+        assert_eq!(run("print 0..1 + 2;").unwrap(), "0..3\n");
+
+        let error = run("1.5..3;").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+    }
+
+    #[test]
+    fn bitwise_not_applies_to_whole_numbers() {
+        assert_eq!(run("print ~0, ~5, ~-1, ~~7;").unwrap(), "-1 -6 0 7\n");
+        let error = run("~2.5;").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(
+            error.message,
+            "Operand of '~' must be a 64-bit whole number, found 2.5"
+        );
+        assert_eq!(run("~\"a\";").unwrap_err().kind, ErrorKind::Type);
+    }
+
+    #[test]
+    fn typeof_names_the_type_of_its_operand() {
+        let output = run("print typeof 1, typeof \"a\", typeof nil, typeof {}, typeof (1..2);");
+        assert_eq!(output.unwrap(), "number string nil map range\n");
+        // it binds like the other prefix operators
+        assert_eq!(run("print typeof 1 + \"!\";").unwrap(), "number!\n");
+    }
+
+    /// golden tests of the printer: every line of `snapshots/*.lox` is parsed and printed in
+    /// each notation, and the result is compared with the `.expected` file next to it. run with
+    /// `UPDATE_SNAPSHOTS=1` to write the files again after an intended change
+    #[test]
+    fn printer_snapshots() {
+        let notations = [
+            ("lisp", Notation::Lisp),
+            ("lisp-compact", Notation::LispCompact),
+            ("rpn", Notation::Rpn),
+            ("polish", Notation::Polish),
+        ];
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        let directory = Path::new(file!()).with_file_name("snapshots");
+        let mut fixtures: Vec<PathBuf> = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "lox"))
+            .collect();
+        fixtures.sort();
+        assert!(
+            !fixtures.is_empty(),
+            "no fixtures in {}",
+            directory.display()
+        );
+
+        for fixture in fixtures {
+            let mut printed = String::new();
+            for line in fs::read_to_string(&fixture).unwrap().lines() {
+                let expr = parse(line);
+                printed.push_str(line);
+                printed.push('\n');
+                for (name, notation) in notations {
+                    let label = format!("{}:", name);
+                    printed.push_str(&format!("  {:<13} {}\n", label, expr.print(notation)));
+                }
+            }
+
+            let expected_path = fixture.with_extension("expected");
+            if update {
+                fs::write(&expected_path, &printed).unwrap();
+                continue;
+            }
+            let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+            assert_eq!(
+                printed,
+                expected,
+                "{} changed, run with UPDATE_SNAPSHOTS=1 if that was intended",
+                fixture.display()
+            );
+        }
+    }
 }
@@ -0,0 +1,327 @@
+use crate::error::json_string;
+use crate::parser::ast::Stmt;
+use crate::parser::expr::{Expr, Literal};
+use crate::parser::value::{format_number, Value};
+use crate::token::{Span, Token};
+
+/// the whole program as an indented JSON document, the machine readable counterpart of
+/// `Expr::print_tree`. every node has a `type` and the `span` of source it was parsed from
+pub fn program_to_json(program: &[Stmt]) -> String {
+    program_to_json_to_depth(program, usize::MAX)
+}
+
+/// like `program_to_json` but nodes nested deeper than `max_depth` (a top-level statement is at
+/// depth 1) keep only their `type` and `span`, marked `"truncated": true`
+pub fn program_to_json_to_depth(program: &[Stmt], max_depth: usize) -> String {
+    let writer = JsonWriter { max_depth };
+    let statements = program.iter().map(|stmt| writer.stmt(stmt, 1)).collect();
+    let mut text = String::new();
+    Json::Object(vec![("statements", Json::Array(statements))]).write(&mut text, 0);
+    text
+}
+
+/// just enough of a JSON document to lay it out with indentation
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String), // already formatted
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn string(s: impl Into<String>) -> Json {
+        Json::String(s.into())
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(&b.to_string()),
+            Json::Number(n) => out.push_str(n),
+            Json::String(s) => out.push_str(&json_string(s)),
+            Json::Array(items) if items.is_empty() => out.push_str("[]"),
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&"  ".repeat(indent + 1));
+                    item.write(out, indent + 1);
+                }
+                out.push('\n');
+                out.push_str(&"  ".repeat(indent));
+                out.push(']');
+            }
+            Json::Object(fields) => {
+                out.push('{');
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&"  ".repeat(indent + 1));
+                    out.push_str(&json_string(name));
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                out.push('\n');
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+            }
+        }
+    }
+}
+
+struct JsonWriter {
+    max_depth: usize,
+}
+
+impl JsonWriter {
+    /// the fields every node starts with, or the whole node when it is past the depth limit
+    /// and only those fields are written
+    fn node(
+        &self,
+        kind: &str,
+        span: Span,
+        depth: usize,
+    ) -> Result<Vec<(&'static str, Json)>, Json> {
+        let fields = vec![
+            ("type", Json::string(kind)),
+            (
+                "span",
+                Json::Object(vec![
+                    ("start", Json::Number(span.start.to_string())),
+                    ("end", Json::Number(span.end.to_string())),
+                ]),
+            ),
+        ];
+        match depth > self.max_depth {
+            true => {
+                let mut fields = fields;
+                fields.push(("truncated", Json::Bool(true)));
+                Err(Json::Object(fields))
+            }
+            false => Ok(fields),
+        }
+    }
+
+    fn name(token: &Token) -> Json {
+        Json::string(token.lexeme.clone())
+    }
+
+    fn stmts(&self, statements: &[Stmt], depth: usize) -> Json {
+        Json::Array(
+            statements
+                .iter()
+                .map(|stmt| self.stmt(stmt, depth))
+                .collect(),
+        )
+    }
+
+    fn stmt(&self, stmt: &Stmt, depth: usize) -> Json {
+        let kind = match stmt {
+            Stmt::Expression { .. } => "Expression",
+            Stmt::Print { .. } => "Print",
+            Stmt::Var { .. } => "Var",
+            Stmt::Const { .. } => "Const",
+            Stmt::Switch { .. } => "Switch",
+            Stmt::ForEach { .. } => "ForEach",
+        };
+        let mut fields = match self.node(kind, stmt.span(), depth) {
+            Ok(fields) => fields,
+            Err(truncated) => return truncated,
+        };
+        let depth = depth + 1;
+        match stmt {
+            Stmt::Expression { expr, .. } => fields.push(("expr", self.expr(expr, depth))),
+            Stmt::Print { values, .. } => {
+                let values = values.iter().map(|value| self.expr(value, depth)).collect();
+                fields.push(("values", Json::Array(values)));
+            }
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                fields.push(("name", Self::name(name)));
+                let initializer = match initializer {
+                    Some(initializer) => self.expr(initializer, depth),
+                    None => Json::Null,
+                };
+                fields.push(("initializer", initializer));
+            }
+            Stmt::Const {
+                name, initializer, ..
+            } => {
+                fields.push(("name", Self::name(name)));
+                fields.push(("initializer", self.expr(initializer, depth)));
+            }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+                ..
+            } => {
+                fields.push(("subject", self.expr(subject, depth)));
+                let cases = cases
+                    .iter()
+                    .map(|(value, body)| {
+                        Json::Object(vec![
+                            ("value", self.expr(value, depth)),
+                            ("body", self.stmts(body, depth)),
+                        ])
+                    })
+                    .collect();
+                fields.push(("cases", Json::Array(cases)));
+                let default = match default {
+                    Some(body) => self.stmts(body, depth),
+                    None => Json::Null,
+                };
+                fields.push(("default", default));
+            }
+            Stmt::ForEach {
+                name,
+                iterable,
+                body,
+                ..
+            } => {
+                fields.push(("name", Self::name(name)));
+                fields.push(("iterable", self.expr(iterable, depth)));
+                fields.push(("body", self.stmt(body, depth)));
+            }
+        }
+        Json::Object(fields)
+    }
+
+    fn expr(&self, expr: &Expr, depth: usize) -> Json {
+        let kind = match expr {
+            Expr::Literal { .. } => "Literal",
+            Expr::Binary { .. } => "Binary",
+            Expr::Grouping { .. } => "Grouping",
+            Expr::Unary { .. } => "Unary",
+            Expr::Ternary { .. } => "Ternary",
+            Expr::Variable { .. } => "Variable",
+            Expr::Assign { .. } => "Assign",
+            Expr::Call { .. } => "Call",
+            Expr::MapLiteral { .. } => "Map",
+            Expr::Index { .. } => "Index",
+            Expr::Get { .. } => "Get",
+            Expr::Range { .. } => "Range",
+            Expr::This { .. } => "This",
+            Expr::Super { .. } => "Super",
+        };
+        let mut fields = match self.node(kind, expr.span(), depth) {
+            Ok(fields) => fields,
+            Err(truncated) => return truncated,
+        };
+        let depth = depth + 1;
+        match expr {
+            Expr::Literal { value, .. } => fields.push(("value", Self::literal(value))),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                fields.push(("operator", Self::name(operator)));
+                fields.push(("left", self.expr(left, depth)));
+                fields.push(("right", self.expr(right, depth)));
+            }
+            Expr::Grouping { expr, .. } => fields.push(("expr", self.expr(expr, depth))),
+            Expr::Unary { operator, right } => {
+                fields.push(("operator", Self::name(operator)));
+                fields.push(("right", self.expr(right, depth)));
+            }
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                fields.push(("condition", self.expr(condition, depth)));
+                fields.push(("then", self.expr(then_expr, depth)));
+                fields.push(("else", self.expr(else_expr, depth)));
+            }
+            Expr::Variable { name } => fields.push(("name", Self::name(name))),
+            Expr::Assign { name, value } => {
+                fields.push(("name", Self::name(name)));
+                fields.push(("value", self.expr(value, depth)));
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                fields.push(("callee", self.expr(callee, depth)));
+                let arguments = arguments.iter().map(|arg| self.expr(arg, depth)).collect();
+                fields.push(("arguments", Json::Array(arguments)));
+            }
+            Expr::MapLiteral { entries, .. } => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        Json::Object(vec![
+                            ("key", self.expr(key, depth)),
+                            ("value", self.expr(value, depth)),
+                        ])
+                    })
+                    .collect();
+                fields.push(("entries", Json::Array(entries)));
+            }
+            Expr::Index { object, index, .. } => {
+                fields.push(("object", self.expr(object, depth)));
+                fields.push(("index", self.expr(index, depth)));
+            }
+            Expr::Get { object, name } => {
+                fields.push(("object", self.expr(object, depth)));
+                fields.push(("name", Self::name(name)));
+            }
+            Expr::Range { start, end, .. } => {
+                fields.push(("start", self.expr(start, depth)));
+                fields.push(("end", self.expr(end, depth)));
+            }
+            Expr::This { .. } => {}
+            Expr::Super { method, .. } => fields.push(("method", Self::name(method))),
+        }
+        Json::Object(fields)
+    }
+
+    /// numbers are written as lox shows them, `nan` and the infinities as strings since JSON has
+    /// no numbers for them
+    fn literal(value: &Literal) -> Json {
+        match value {
+            Literal::Number(n) if n.is_finite() => Json::Number(format_number(*n)),
+            Literal::Number(n) => Json::string(format_number(*n)),
+            Literal::String(s) => Json::string(s.to_string()),
+            Literal::Bytes(bytes) => Json::string(Value::Bytes(bytes.clone()).to_string()),
+            Literal::Bool(b) => Json::Bool(*b),
+            Literal::Nil => Json::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::lox::Lox;
+
+    fn json(source: &str) -> String {
+        program_to_json(&Lox::new().parse_program(source).unwrap())
+    }
+
+    #[test]
+    fn statements_are_the_top_level_array() {
+        let text = json("var a = 1; print a; a = 2; switch (a) { case 2: print a; }");
+        assert!(text.starts_with("{\n  \"statements\": [\n"));
+        // one object per top-level statement, at the indentation of the array's items
+        let statements = text.lines().filter(|line| *line == "    {").count();
+        assert_eq!(statements, 4);
+        assert_eq!(json(""), "{\n  \"statements\": []\n}");
+    }
+
+    #[test]
+    fn nodes_carry_their_type_and_span() {
+        let text = json("print 1 + 2;");
+        for node in [
+            "\"type\": \"Print\"",
+            "\"type\": \"Binary\"",
+            "\"operator\": \"+\"",
+        ] {
+            assert!(text.contains(node), "{} not in {}", node, text);
+        }
+        assert!(text.contains("\"span\": {\n        \"start\": 0,\n        \"end\": 12\n      }"));
+    }
+}
@@ -0,0 +1,368 @@
+use crate::error::{Diagnostics, Error, Position};
+use crate::parser::ast::Stmt;
+use crate::parser::expr::{Expr, Literal};
+use crate::token::Token;
+use std::collections::HashSet;
+
+/// optional checks over a parsed program, each finding is a non-fatal warning
+pub fn lint(program: &[Stmt], diagnostics: &mut Diagnostics) {
+    let mut linter = Linter {
+        diagnostics,
+        declared: Vec::new(),
+        read: HashSet::new(),
+    };
+    linter.lint_stmts(program);
+    linter.report_unused();
+}
+
+struct Linter<'a> {
+    diagnostics: &'a mut Diagnostics,
+    declared: Vec<Token>,  // names of every var and const declaration
+    read: HashSet<String>, // names read anywhere, assigning doesn't count
+}
+
+impl Linter<'_> {
+    fn lint_stmts(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.lint_stmt(statement);
+        }
+    }
+
+    /// declared variables that are never read, there are only globals so the whole program is
+    /// their scope
+    fn report_unused(&mut self) {
+        for name in &self.declared {
+            if !self.read.contains(&name.lexeme) {
+                self.diagnostics.push(
+                    Error::warning(
+                        format!("Variable '{}' is never used", name.lexeme),
+                        Position::new(name.line, name.column, name.offset),
+                    )
+                    .with_help("Remove the declaration or check the name for typos"),
+                );
+            }
+        }
+    }
+
+    /// `cond ? doThis() : doThat();` only branches for the side effects, its value is thrown away.
+    /// lox has no `if` yet, so the help points at the statement form that does exist
+    fn check_ternary_statement(&mut self, expr: &Expr) {
+        if let Expr::Ternary { question, .. } = expr {
+            self.diagnostics.push(
+                Error::warning(
+                    "Ternary used as a statement",
+                    Position::new(question.line, question.column, question.offset),
+                )
+                .with_help("Branching for side effects reads better as a statement, such as a switch on the condition"),
+            );
+        }
+    }
+
+    /// `(x = 1) ? a : b` and `x = 1 ? a : b` assign where `x == 1` was most likely meant. an
+    /// assignment wrapped in another pair of parentheses, `((x = 1)) ? a : b`, is taken as
+    /// intended, like `x = (1 ? a : b)` is
+    fn check_assignment_condition(&mut self, expr: &Expr) {
+        let name = match expr {
+            Expr::Ternary { condition, .. } => match &**condition {
+                Expr::Grouping { expr, .. } => match &**expr {
+                    Expr::Assign { name, .. } => name,
+                    _ => return,
+                },
+                _ => return,
+            },
+            // `=` binds looser than `?:`, so the ternary ends up as the assigned value
+            Expr::Assign { name, value } => match &**value {
+                Expr::Ternary { condition, .. } if matches!(**condition, Expr::Literal { .. }) => {
+                    name
+                }
+                _ => return,
+            },
+            _ => return,
+        };
+        self.diagnostics.push(
+            Error::warning(
+                "Assignment used as a ternary condition",
+                Position::new(name.line, name.column, name.offset),
+            )
+            .with_help(
+                "Did you mean '=='? Add parentheses around the assignment if it is intended",
+            ),
+        );
+    }
+
+    fn lint_stmt(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Expression {
+                expr,
+                semicolon: Some(_),
+            } if !has_side_effects(expr) => {
+                // without the ';' the value is printed by the REPL, so it isn't discarded
+                let token = first_token(expr);
+                self.diagnostics.push(
+                    Error::warning(
+                        "Expression result is unused",
+                        Position::new(token.line, token.column, token.offset),
+                    )
+                    .with_help("The value is discarded, print it or remove the statement"),
+                );
+                self.lint_expr(expr);
+            }
+            Stmt::Expression {
+                expr,
+                semicolon: Some(_),
+            } => {
+                // only reached with side effects, otherwise the result is unused as a whole
+                self.check_ternary_statement(expr);
+                self.lint_expr(expr);
+            }
+            Stmt::Expression { expr, .. } => self.lint_expr(expr),
+            Stmt::Print { values, .. } => {
+                for value in values {
+                    self.lint_expr(value);
+                }
+            }
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                self.declared.push(name.clone());
+                if let Some(initializer) = initializer {
+                    self.lint_expr(initializer);
+                }
+            }
+            Stmt::Const {
+                name, initializer, ..
+            } => {
+                self.declared.push(name.clone());
+                self.lint_expr(initializer);
+            }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+                ..
+            } => {
+                self.lint_expr(subject);
+                for (value, body) in cases {
+                    self.lint_expr(value);
+                    self.lint_stmts(body);
+                }
+                if let Some(body) = default {
+                    self.lint_stmts(body);
+                }
+            }
+            Stmt::ForEach {
+                name,
+                iterable,
+                body,
+                ..
+            } => {
+                self.declared.push(name.clone());
+                self.lint_expr(iterable);
+                self.lint_stmt(body);
+            }
+        }
+    }
+
+    fn lint_expr(&mut self, expr: &Expr) {
+        self.check_assignment_condition(expr);
+        match expr {
+            Expr::Literal { .. } | Expr::This { .. } | Expr::Super { .. } => {}
+            Expr::Variable { name } => {
+                self.read.insert(name.lexeme.clone());
+            }
+            Expr::Grouping { expr, .. } => self.lint_expr(expr),
+            Expr::Unary { right, .. } => self.lint_expr(right),
+            Expr::Binary { left, right, .. } => {
+                self.lint_expr(left);
+                self.lint_expr(right);
+            }
+            Expr::Ternary {
+                condition,
+                question,
+                then_expr,
+                else_expr,
+            } => {
+                if let (Some(then_type), Some(else_type)) =
+                    (literal_type(then_expr), literal_type(else_expr))
+                {
+                    if then_type != else_type {
+                        self.diagnostics.push(
+                            Error::warning(
+                                format!(
+                                    "Ternary branches have different types: {} and {}",
+                                    then_type, else_type
+                                ),
+                                Position::new(question.line, question.column, question.offset),
+                            )
+                            .with_help("Both branches of '?:' usually produce the same type"),
+                        );
+                    }
+                }
+                self.lint_expr(condition);
+                self.lint_expr(then_expr);
+                self.lint_expr(else_expr);
+            }
+            Expr::Assign { value, .. } => self.lint_expr(value),
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.lint_expr(callee);
+                for argument in arguments {
+                    self.lint_expr(argument);
+                }
+            }
+            Expr::MapLiteral { entries, .. } => {
+                for (key, value) in entries {
+                    self.lint_expr(key);
+                    self.lint_expr(value);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.lint_expr(object);
+                self.lint_expr(index);
+            }
+            Expr::Get { object, .. } => self.lint_expr(object),
+            Expr::Range { start, end, .. } => {
+                self.lint_expr(start);
+                self.lint_expr(end);
+            }
+        }
+    }
+}
+
+/// type of a literal branch, looking through parentheses, `None` for anything else
+fn literal_type(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::Grouping { expr, .. } => literal_type(expr),
+        Expr::Literal { value, .. } => Some(match value {
+            Literal::Number(_) => "number",
+            Literal::String(_) => "string",
+            Literal::Bytes(_) => "bytes",
+            Literal::Bool(_) => "boolean",
+            Literal::Nil => "nil",
+        }),
+        _ => None,
+    }
+}
+
+/// whether evaluating the expression can change anything: calls and assignments can, the rest
+/// only computes a value (or fails)
+fn has_side_effects(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call { .. } | Expr::Assign { .. } => true,
+        Expr::Literal { .. } | Expr::Variable { .. } | Expr::This { .. } | Expr::Super { .. } => {
+            false
+        }
+        Expr::Grouping { expr, .. } => has_side_effects(expr),
+        Expr::Unary { right, .. } => has_side_effects(right),
+        Expr::Binary { left, right, .. } => has_side_effects(left) || has_side_effects(right),
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            has_side_effects(condition)
+                || has_side_effects(then_expr)
+                || has_side_effects(else_expr)
+        }
+        Expr::MapLiteral { entries, .. } => entries
+            .iter()
+            .any(|(key, value)| has_side_effects(key) || has_side_effects(value)),
+        Expr::Index { object, index, .. } => has_side_effects(object) || has_side_effects(index),
+        Expr::Get { object, .. } => has_side_effects(object),
+        Expr::Range { start, end, .. } => has_side_effects(start) || has_side_effects(end),
+    }
+}
+
+/// the token an expression starts with, where warnings about the whole expression point
+fn first_token(expr: &Expr) -> &Token {
+    match expr {
+        Expr::Literal { token, .. } => token,
+        Expr::Variable { name } | Expr::Assign { name, .. } => name,
+        Expr::Grouping { opening, .. } => opening,
+        Expr::Unary { operator, .. } => operator,
+        Expr::MapLiteral { brace, .. } => brace,
+        Expr::This { keyword } | Expr::Super { keyword, .. } => keyword,
+        Expr::Binary { left: start, .. }
+        | Expr::Ternary {
+            condition: start, ..
+        }
+        | Expr::Call { callee: start, .. }
+        | Expr::Index { object: start, .. }
+        | Expr::Get { object: start, .. }
+        | Expr::Range { start, .. } => first_token(start),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::lox::Lox;
+
+    /// the messages of the warnings the lints find in the source
+    fn warnings(source: &str) -> Vec<String> {
+        let mut lox = Lox::new();
+        lox.lint = true;
+        let (_, diagnostics) = lox.parse_with_diagnostics(source);
+        diagnostics
+            .iter()
+            .filter(|error| error.is_warning())
+            .map(|error| error.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn ternary_with_branches_of_different_types_warns() {
+        assert_eq!(
+            warnings("print true ? 1 : \"x\";"),
+            ["Ternary branches have different types: number and string"]
+        );
+        assert!(warnings("print true ? 1 : (2);").is_empty());
+        // only literals are compared, a variable could hold anything
+        assert!(warnings("var x = 1; print true ? 1 : x;").is_empty());
+    }
+
+    #[test]
+    fn variable_never_read_warns() {
+        assert_eq!(
+            warnings("var x = 1; x = 2;"),
+            ["Variable 'x' is never used"]
+        );
+        assert!(warnings("var x = 1; print x;").is_empty());
+        assert!(warnings("const x = 1; print {x: x};").is_empty());
+    }
+
+    #[test]
+    fn discarded_pure_expression_warns() {
+        assert_eq!(warnings("1 + 2;"), ["Expression result is unused"]);
+        // side effects are why the statement is there
+        assert!(warnings("println(1);").is_empty());
+        assert!(warnings("var x; x = 1 + 2; print x;").is_empty());
+        // without the ';' the REPL prints the value
+        assert!(warnings("1 + 2").is_empty());
+    }
+
+    #[test]
+    fn ternary_run_for_its_side_effects_warns() {
+        assert_eq!(
+            warnings("true ? println(1) : println(2);"),
+            ["Ternary used as a statement"]
+        );
+        // the value is used
+        assert!(warnings("print true ? 1 : 2;").is_empty());
+        assert!(warnings("var x = true ? println(1) : 2; print x;").is_empty());
+    }
+
+    #[test]
+    fn assignment_as_ternary_condition_warns() {
+        let flagged = "Assignment used as a ternary condition";
+        assert_eq!(warnings("var x; print (x = 1) ? 2 : 3, x;"), [flagged]);
+        assert_eq!(warnings("var x; print x = 1 ? 2 : 3, x;"), [flagged]);
+
+        // extra parentheses mark the assignment as intended
+        assert!(warnings("var x; print ((x = 1)) ? 2 : 3, x;").is_empty());
+        assert!(warnings("var x; print x = (1 ? 2 : 3), x;").is_empty());
+        // assigning the result of a ternary over a variable is ordinary
+        assert!(warnings("var x; var y = true; print x = y ? 2 : 3, x;").is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::parser::value::{Key, Value};
+use std::collections::HashMap;
+
+/// the storage of a map value. iteration (printing, `for (k in map)`) follows insertion order,
+/// so a program shows the same output on every run. assigning an existing key keeps its place
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(Key, Value)>,
+    indexes: HashMap<Key, usize>, // where each key sits in `entries`
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Map::default()
+    }
+
+    /// bind a key to a value, a new key goes after every existing one
+    pub fn insert(&mut self, key: Key, value: Value) {
+        match self.indexes.get(&key) {
+            Some(&index) => self.entries[index].1 = value,
+            None => {
+                self.indexes.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Value> {
+        self.indexes.get(key).map(|&index| &self.entries[index].1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// the entries in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&Key, &Value)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    /// the keys in insertion order
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+}
+
+/// maps are equal when they hold the same entries, whatever order they were inserted in
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl FromIterator<(Key, Value)> for Map {
+    fn from_iter<I: IntoIterator<Item = (Key, Value)>>(entries: I) -> Self {
+        let mut map = Map::new();
+        for (key, value) in entries {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::interpreter::runtime::test_support::{lox, run};
+
+    #[test]
+    fn map_literal_is_built_and_read_by_key() {
+        let source = "var m = {\"a\": 1, 2: \"two\", nil: true};
+            print m; print m[\"a\"]; print m[2]; print m[nil];";
+        assert_eq!(
+            run(source).unwrap(),
+            "{\"a\": 1, 2: \"two\", nil: true}\n1\ntwo\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn missing_key_reads_nil_unless_keys_are_strict() {
+        let (mut lox, output) = lox();
+        lox.run("var m = {\"a\": 1}; print m[\"b\"];").unwrap();
+        assert_eq!(output.contents(), "nil\n");
+
+        lox.interpreter.strict_keys = true;
+        let error = lox.run("m[\"b\"];").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "Key \"b\" not found in map");
+    }
+
+    #[test]
+    fn iteration_follows_insertion_order() {
+        let output =
+            run("var m = {\"z\": 1, 3: 2, \"a\": 3, nil: 4}; for (k in m) print k; print m;");
+        assert_eq!(
+            output.unwrap(),
+            "z\n3\na\nnil\n{\"z\": 1, 3: 2, \"a\": 3, nil: 4}\n"
+        );
+
+        // assigning an existing key keeps its place
+        let mut map: Map = [("b", 1.0), ("a", 2.0), ("b", 3.0), ("c", 4.0)]
+            .into_iter()
+            .map(|(key, value)| (Key::String(key.into()), Value::Number(value)))
+            .collect();
+        map.insert(Key::String("a".into()), Value::Nil);
+        let entries: Vec<String> = map
+            .iter()
+            .map(|(key, value)| format!("{}={}", key.to_value(), value))
+            .collect();
+        assert_eq!(entries, ["b=3", "a=nil", "c=4"]);
+    }
+}
@@ -1,4 +1,8 @@
 pub mod parser;
 pub mod expr;
-mod ast;
-mod value;
\ No newline at end of file
+pub mod lint;
+pub mod ast;
+pub mod precedence;
+pub mod value;
+pub mod map;
+pub mod json;
\ No newline at end of file
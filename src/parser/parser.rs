@@ -1,129 +1,320 @@
-use crate::error::Position;
+use crate::error::{Diagnostics, Position};
+use crate::lexer::scanner::unescape_bytes;
 use crate::{
     error::Error,
+    parser::ast::Stmt,
     parser::expr::{Expr, Literal},
+    parser::precedence::{self, Associativity},
     token::{Token, TokenKind},
 };
+
+/// upper bound on the arguments of a single call, checked while parsing.
+/// whether a call passes as many arguments as the callee expects is checked when it runs
+pub const MAX_ARGUMENTS: usize = 255;
+
+/// how deep groupings, unary and right operands may nest before parsing gives up instead of
+/// overflowing the stack. in a debug build a level takes up to ~15 KiB to parse or evaluate
+/// (~2 KiB in release), so this stays under 4 MiB of the 8 MiB main thread stack
+pub const MAX_DEPTH: usize = 256;
+
+/// how many operators a left associative chain like `1 + 1 + 1` may have. parsing it doesn't
+/// recurse, but evaluating it does, up to ~10 KiB per operator in a debug build
+pub const MAX_CHAIN: usize = 256;
+
 #[derive(Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    depth: usize,        // how deep the node being parsed sits in the tree
+    allow_ternary: bool, // `?:` is a parse error when unset
 }
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        // comments only matter to tooling, the grammar never sees them
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Comment)
+            .collect();
+        Self {
+            tokens,
+            current: 0,
+            depth: 0,
+            allow_ternary: true,
+        }
+    }
+
+    /// allow the `?:` operator (the default), turning it off lets a teaching subset of the
+    /// language reject it with a clear error
+    pub fn ternary(mut self, allow: bool) -> Self {
+        self.allow_ternary = allow;
+        self
     }
 
-    pub fn parse(&mut self) -> Result<Expr, String> {
+    /// parse a whole expression, empty input parses as nil
+    pub fn parse(&mut self) -> Result<Expr, Error> {
+        if self.is_at_end() {
+            return Ok(Expr::Literal {
+                value: Literal::Nil,
+                token: self.peek().clone(),
+            });
+        }
         self.expression()
     }
-    fn expression(&mut self) -> Result<Expr, String> {
-        self.equality()
+
+    /// parse statements up to the end of input
+    pub fn parse_program(&mut self) -> Result<Vec<Stmt>, Error> {
+        let mut diagnostics = Diagnostics::new();
+        let statements = self.parse_with_diagnostics(&mut diagnostics);
+        match diagnostics.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(statements),
+        }
     }
-    fn comma(&mut self) -> Result<Expr, String> {
-        let mut expr = self.ternary()?;
 
-        if self.match_token(&[TokenKind::Comma]) {
-            let operator = self.previous().clone();
-            let right = self.ternary()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
+    /// parse every statement, recording each error and resuming at the next statement
+    pub fn parse_with_diagnostics(&mut self, diagnostics: &mut Diagnostics) -> Vec<Stmt> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            match self.statement() {
+                Ok(statement) => statements.push(statement),
+                Err(error) => {
+                    diagnostics.push(error);
+                    self.synchronize();
+                }
             }
         }
-        Ok(expr)
+        statements
     }
 
-    fn ternary(&mut self) -> Result<Expr, String> {
-        let expr = self.equality()?;
-
-        if self.match_token(&[TokenKind::Question]) {
-            let then_expr = self.expression()?;
-
-            if !self.match_token(&[TokenKind::Colon]) {
-                return Err("Expected ':' after then expression in ternary operator".to_string());
+    /// skip tokens until a likely statement boundary, so one mistake doesn't cascade
+    fn synchronize(&mut self) {
+        self.advance();
+        while !self.is_at_end() {
+            if self.previous().kind == TokenKind::Semicolon {
+                return;
+            }
+            match self.peek().kind {
+                TokenKind::Class
+                | TokenKind::Fun
+                | TokenKind::Var
+                | TokenKind::Const
+                | TokenKind::For
+                | TokenKind::If
+                | TokenKind::While
+                | TokenKind::Print
+                | TokenKind::Return
+                | TokenKind::Switch => return,
+                _ => {
+                    self.advance();
+                }
             }
+        }
+    }
 
-            let else_expr = self.ternary()?; // right associative
+    fn statement(&mut self) -> Result<Stmt, Error> {
+        // statements nest inside each other through their bodies, like expressions do
+        let depth = self.depth;
+        let statement = self.nest().and_then(|_| {
+            if self.match_token(&[TokenKind::Var]) {
+                self.var_declaration()
+            } else if self.match_token(&[TokenKind::Const]) {
+                self.const_declaration()
+            } else if self.match_token(&[TokenKind::Print]) {
+                self.print_statement()
+            } else if self.match_token(&[TokenKind::Switch]) {
+                self.switch_statement()
+            } else if self.match_token(&[TokenKind::For]) {
+                self.for_statement()
+            } else {
+                self.expression_statement()
+            }
+        });
+        self.depth = depth;
+        statement
+    }
 
-            return Ok(Expr::Ternary {
-                condition: Box::new(expr),
-                then_expr: Box::new(then_expr),
-                else_expr: Box::new(else_expr),
-            });
+    fn var_declaration(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        let name = self.consume(TokenKind::Identifier, "Expected variable name.")?;
+        let mut initializer = None;
+        if self.match_token(&[TokenKind::Equal]) {
+            initializer = Some(self.expression()?);
         }
-        Ok(expr)
+        let semicolon = self.consume(
+            TokenKind::Semicolon,
+            "Expected ';' after variable declaration.",
+        )?;
+        Ok(Stmt::Var {
+            keyword,
+            name,
+            initializer,
+            semicolon,
+        })
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
-        let mut expr = self.comparison()?;
+    fn const_declaration(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        let name = self.consume(TokenKind::Identifier, "Expected constant name.")?;
+        self.consume(TokenKind::Equal, "Expected '=' after constant name.")?;
+        let initializer = self.expression()?;
+        let semicolon = self.consume(
+            TokenKind::Semicolon,
+            "Expected ';' after constant declaration.",
+        )?;
+        Ok(Stmt::Const {
+            keyword,
+            name,
+            initializer,
+            semicolon,
+        })
+    }
 
-        while self.match_token(&[TokenKind::BangEqual, TokenKind::Equal]) {
-            let operator = self.previous();
-            let right = self.comparison()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+    fn print_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        // the commas separate values here, `expression` stops before the comma operator
+        let mut values = vec![self.expression()?];
+        while self.match_token(&[TokenKind::Comma]) {
+            values.push(self.expression()?);
         }
-        Ok(expr)
+        let semicolon = self.consume(TokenKind::Semicolon, "Expected ';' after value.")?;
+        Ok(Stmt::Print {
+            keyword,
+            values,
+            semicolon,
+        })
     }
-    fn comparison(&mut self) -> Result<Expr, String> {
-        let mut expr = self.term()?;
-
-        while self.match_token(&[
-            TokenKind::Greater,
-            TokenKind::GreaterEqual,
-            TokenKind::Less,
-            TokenKind::LessEqual,
-        ]) {
-            let operator = self.previous();
-            let right = self.term()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+
+    fn switch_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        self.consume(TokenKind::LeftParen, "Expected '(' after 'switch'.")?;
+        let subject = self.expression()?;
+        self.consume(TokenKind::RightParen, "Expected ')' after switch subject.")?;
+        self.consume(TokenKind::LeftBrace, "Expected '{' before switch cases.")?;
+
+        let mut cases = Vec::new();
+        while self.match_token(&[TokenKind::Case]) {
+            let value = self.expression()?;
+            self.consume(TokenKind::Colon, "Expected ':' after case value.")?;
+            cases.push((value, self.case_body()?));
         }
-        Ok(expr)
+
+        let mut default = None;
+        if self.match_token(&[TokenKind::Default]) {
+            self.consume(TokenKind::Colon, "Expected ':' after 'default'.")?;
+            default = Some(self.case_body()?);
+        }
+
+        let closing = self.consume(TokenKind::RightBrace, "Expected '}' after switch cases.")?;
+        Ok(Stmt::Switch {
+            keyword,
+            subject,
+            cases,
+            default,
+            closing,
+        })
     }
 
-    fn term(&mut self) -> Result<Expr, String> {
-        let mut expr = self.factor()?;
+    /// `for (x in iterable) body`, the only loop so far
+    fn for_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        self.consume(TokenKind::LeftParen, "Expected '(' after 'for'.")?;
+        let name = self.consume(TokenKind::Identifier, "Expected loop variable name.")?;
+        self.consume(TokenKind::In, "Expected 'in' after loop variable.")?;
+        let iterable = self.expression()?;
+        self.consume(TokenKind::RightParen, "Expected ')' after for clauses.")?;
+        let body = self.statement()?;
+        Ok(Stmt::ForEach {
+            keyword,
+            name,
+            iterable,
+            body: Box::new(body),
+        })
+    }
 
-        while self.match_token(&[TokenKind::Plus, TokenKind::Minus]) {
-            let operator = self.previous();
-            let right = self.factor()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+    /// statements of a case, up to the next case, the default or the end of the switch
+    fn case_body(&mut self) -> Result<Vec<Stmt>, Error> {
+        let mut body = Vec::new();
+        while !self.check(TokenKind::Case)
+            && !self.check(TokenKind::Default)
+            && !self.check(TokenKind::RightBrace)
+            && !self.is_at_end()
+        {
+            body.push(self.statement()?);
         }
-        Ok(expr)
+        Ok(body)
     }
 
-    fn factor(&mut self) -> Result<Expr, String> {
-        let mut expr = self.unary()?;
+    fn expression_statement(&mut self) -> Result<Stmt, Error> {
+        let expr = self.expression()?;
+        // the last expression of the input may leave out its ';', the REPL prints its value
+        if self.is_at_end() {
+            return Ok(Stmt::Expression {
+                expr,
+                semicolon: None,
+            });
+        }
+        let semicolon = self.consume(TokenKind::Semicolon, "Expected ';' after expression.")?;
+        Ok(Stmt::Expression {
+            expr,
+            semicolon: Some(semicolon),
+        })
+    }
 
-        while self.match_token(&[TokenKind::Slash, TokenKind::Star]) {
-            let operator = self.previous();
-            let right = self.unary()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+    fn expression(&mut self) -> Result<Expr, Error> {
+        self.parse_precedence(precedence::ASSIGNMENT)
+    }
+    fn comma(&mut self) -> Result<Expr, Error> {
+        self.parse_precedence(precedence::COMMA)
+    }
+
+    /// parse an expression whose operators all bind at least as tight as `min`
+    fn parse_precedence(&mut self, min: u8) -> Result<Expr, Error> {
+        let depth = self.depth;
+        let expr = self.nest().and_then(|_| self.climb(min));
+        self.depth = depth;
+        expr
+    }
+
+    fn climb(&mut self, min: u8) -> Result<Expr, Error> {
+        let mut expr = self.prefix()?;
+        let mut chained = 0;
+
+        while let Some(precedence) = precedence::precedence(self.peek().kind) {
+            if precedence < min {
+                break;
+            }
+            chained += 1;
+            if chained > MAX_CHAIN {
+                let token = self.peek();
+                return Err(Error::parse(
+                    "Too many chained operators",
+                    Position::new(token.line, token.column, token.offset),
+                )
+                .with_help("Split the expression using variables"));
+            }
+            let operator = self.advance();
+            expr = self.infix(expr, operator, precedence)?;
         }
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, String> {
-        if self.match_token(&[TokenKind::Bang, TokenKind::Minus]) {
-            let operator = self.previous();
-            let right = self.unary()?;
+    /// go one level deeper into the tree, failing cleanly past MAX_DEPTH
+    fn nest(&mut self) -> Result<(), Error> {
+        if self.depth >= MAX_DEPTH {
+            let token = self.peek();
+            return Err(Error::parse(
+                "Maximum nesting depth exceeded",
+                Position::new(token.line, token.column, token.offset),
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn prefix(&mut self) -> Result<Expr, Error> {
+        if let Some(precedence) = precedence::prefix_precedence(self.peek().kind) {
+            let operator = self.advance();
+            let right = self.parse_precedence(precedence)?;
 
             return Ok(Expr::Unary {
                 operator,
@@ -133,42 +324,251 @@ impl Parser {
         self.primary()
     }
 
-    fn primary(&mut self) -> Result<Expr, String> {
+    /// parse the rest of an operator whose left operand and token were already consumed
+    fn infix(&mut self, left: Expr, operator: Token, precedence: u8) -> Result<Expr, Error> {
+        // operands of a left associative operator must bind tighter than the operator itself
+        let right_precedence = match precedence::associativity(operator.kind) {
+            Some(Associativity::Right) => precedence,
+            _ => precedence + 1,
+        };
+
+        match operator.kind {
+            TokenKind::LeftParen => self.finish_call(left),
+            TokenKind::Equal | TokenKind::PercentEqual => {
+                let Expr::Variable { name } = left else {
+                    return Err(Error::parse(
+                        "Invalid assignment target.",
+                        Position::new(operator.line, operator.column, operator.offset),
+                    ));
+                };
+                let mut value = self.parse_precedence(right_precedence)?;
+
+                // `x %= y` is sugar for `x = x % y`
+                if operator.kind == TokenKind::PercentEqual {
+                    let mut percent = operator.clone();
+                    percent.kind = TokenKind::Percent;
+                    percent.lexeme = "%".into();
+                    value = Expr::Binary {
+                        left: Box::new(Expr::Variable { name: name.clone() }),
+                        operator: percent,
+                        right: Box::new(value),
+                    };
+                }
+
+                Ok(Expr::Assign {
+                    name,
+                    value: Box::new(value),
+                })
+            }
+            TokenKind::LeftBracket => {
+                let index = self.expression()?;
+                let closing = self.consume(TokenKind::RightBracket, "Expected ']' after index.")?;
+
+                Ok(Expr::Index {
+                    object: Box::new(left),
+                    bracket: operator,
+                    index: Box::new(index),
+                    closing,
+                })
+            }
+            TokenKind::Dot => {
+                let name =
+                    self.consume(TokenKind::Identifier, "Expected property name after '.'.")?;
+                Ok(Expr::Get {
+                    object: Box::new(left),
+                    name,
+                })
+            }
+            TokenKind::DotDot => {
+                let end = self.parse_precedence(right_precedence)?;
+                Ok(Expr::Range {
+                    start: Box::new(left),
+                    dots: operator,
+                    end: Box::new(end),
+                })
+            }
+            TokenKind::Question if !self.allow_ternary => Err(Error::parse(
+                "Ternary operator is disabled in this mode",
+                Position::new(operator.line, operator.column, operator.offset),
+            )
+            .with_help("Use a switch statement instead")),
+            TokenKind::Question => {
+                let then_expr = self.expression()?;
+
+                self.consume(
+                    TokenKind::Colon,
+                    "Expected ':' after then expression in ternary operator",
+                )?;
+
+                let else_expr = self.parse_precedence(right_precedence)?;
+
+                Ok(Expr::Ternary {
+                    condition: Box::new(left),
+                    question: operator,
+                    then_expr: Box::new(then_expr),
+                    else_expr: Box::new(else_expr),
+                })
+            }
+            // `(1, 2,)` like `f(1, 2,)`, the comma expression only appears inside parentheses
+            TokenKind::Comma if self.check(TokenKind::RightParen) => Ok(left),
+            _ => {
+                if precedence == precedence::COMPARISON && Self::is_comparison(&left) {
+                    return Err(Error::parse(
+                        "Comparison operators can't be chained",
+                        Position::new(operator.line, operator.column, operator.offset),
+                    )
+                    .with_help(
+                        "'a < b < c' compares the boolean 'a < b' with c, \
+                         compare each pair on its own: a < b ? b < c : false",
+                    ));
+                }
+                let right = self.parse_precedence(right_precedence)?;
+                Ok(Expr::Binary {
+                    left: Box::new(left),
+                    operator,
+                    right: Box::new(right),
+                })
+            }
+        }
+    }
+
+    /// an unparenthesized `<`, `<=`, `>` or `>=`, `(a < b) < c` is left alone as it's explicit
+    fn is_comparison(expr: &Expr) -> bool {
+        matches!(expr, Expr::Binary { operator, .. }
+            if precedence::precedence(operator.kind) == Some(precedence::COMPARISON))
+    }
+
+    /// parse the argument list of a call whose '(' was already consumed
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, Error> {
+        let mut arguments = Vec::new();
+
+        if !self.check(TokenKind::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    let token = self.peek();
+                    return Err(Error::parse(
+                        format!("Can't have more than {} arguments.", MAX_ARGUMENTS),
+                        Position::new(token.line, token.column, token.offset),
+                    ));
+                }
+                arguments.push(self.expression()?);
+                // a single trailing comma before ')' is allowed
+                if !self.match_token(&[TokenKind::Comma]) || self.check(TokenKind::RightParen) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(TokenKind::RightParen, "Expected ')' after arguments.")?;
+        Ok(Expr::Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
         if self.match_token(&[TokenKind::False]) {
-            return Ok(Expr::Literal(Literal::Bool(false)));
+            return Ok(self.literal(Literal::Bool(false)));
         }
 
         if self.match_token(&[TokenKind::True]) {
-            return Ok(Expr::Literal(Literal::Bool(true)));
+            return Ok(self.literal(Literal::Bool(true)));
         }
 
         if self.match_token(&[TokenKind::Nil]) {
-            return Ok(Expr::Literal(Literal::Nil));
+            return Ok(self.literal(Literal::Nil));
         }
 
         if self.match_token(&[TokenKind::Number]) {
-            return Ok(Expr::Literal(Literal::Number(
-                self.previous().lexeme.parse::<f64>().unwrap(),
-            )));
+            let value = self.previous().lexeme.parse::<f64>().unwrap();
+            return Ok(self.literal(Literal::Number(value)));
+        }
+
+        if self.match_token(&[TokenKind::Bytes]) {
+            // the scanner already rejected invalid escapes
+            let value = unescape_bytes(&self.previous().lexeme).unwrap_or_default();
+            return Ok(self.literal(Literal::Bytes(value)));
         }
 
         if self.match_token(&[TokenKind::String]) {
-            return Ok(Expr::Literal(Literal::String(
-                self.previous().lexeme.clone(),
-            )));
+            let value = self.previous().lexeme.as_str().into();
+            return Ok(self.literal(Literal::String(value)));
+        }
+
+        if self.match_token(&[TokenKind::Identifier]) {
+            return Ok(Expr::Variable {
+                name: self.previous(),
+            });
+        }
+
+        // where `this` and `super` may appear is left to the resolver, the grammar allows them
+        // in any expression
+        if self.match_token(&[TokenKind::This]) {
+            return Ok(Expr::This {
+                keyword: self.previous(),
+            });
+        }
+
+        if self.match_token(&[TokenKind::Super]) {
+            let keyword = self.previous();
+            self.consume(TokenKind::Dot, "Expected '.' after 'super'.")?;
+            let method = self.consume(TokenKind::Identifier, "Expected superclass method name.")?;
+            return Ok(Expr::Super { keyword, method });
         }
 
         if self.match_token(&[TokenKind::LeftParen]) {
-            let expr = self.expression()?;
+            let opening = self.previous();
+            // `(1, 2, 3)` is a comma expression giving its last value, not a tuple
+            let expr = self.comma()?;
 
-            return match self.consume(TokenKind::RightParen, "Expected ')' after expression.") {
-                Ok(_token) => Ok(Expr::Grouping {
-                    expr: Box::new(expr),
-                }),
-                Err(error) => Err(error.message),
-            };
+            let closing = self.consume(TokenKind::RightParen, "Expected ')' after expression.")?;
+            return Ok(Expr::Grouping {
+                opening,
+                expr: Box::new(expr),
+                closing,
+            });
+        }
+        if self.match_token(&[TokenKind::LeftBrace]) {
+            return self.map_literal();
+        }
+        let token = self.peek();
+        Err(Error::parse(
+            format!("Expected expression, found {}", token.kind),
+            Position::new(token.line, token.column, token.offset),
+        ))
+    }
+
+    /// parse the `key: value` entries of a map literal whose '{' was already consumed
+    fn map_literal(&mut self) -> Result<Expr, Error> {
+        let brace = self.previous();
+        let mut entries = Vec::new();
+
+        while !self.check(TokenKind::RightBrace) {
+            let key = self.expression()?;
+            self.consume(TokenKind::Colon, "Expected ':' after map key.")?;
+            let value = self.expression()?;
+            entries.push((key, value));
+
+            if !self.match_token(&[TokenKind::Comma]) {
+                break;
+            }
+        }
+
+        let closing = self.consume(TokenKind::RightBrace, "Expected '}' after map entries.")?;
+        Ok(Expr::MapLiteral {
+            brace,
+            entries,
+            closing,
+        })
+    }
+
+    /// Build a literal node remembering the token it was parsed from
+    fn literal(&self, value: Literal) -> Expr {
+        Expr::Literal {
+            value,
+            token: self.previous(),
         }
-        Err(format!("Unexpected token: {:?}", self.peek()))
     }
 
     /// Check if the current token has any of the given types
@@ -223,3 +623,370 @@ impl Parser {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::interpreter::lox::Lox;
+    use crate::interpreter::natives::random::Rng;
+    use crate::interpreter::runtime::test_support::run;
+    use crate::parser::expr::Notation;
+    use crate::parser::precedence::{EQUALITY, FACTOR, TERM, UNARY};
+
+    #[test]
+    fn unexpected_token_message_reads_naturally() {
+        let error = Lox::new().parse(")").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.message, "Expected expression, found ')'");
+    }
+
+    #[test]
+    fn equal_sources_parse_to_equal_trees() {
+        let lox = Lox::new();
+        assert_eq!(
+            lox.parse("1 + 2 * x").unwrap(),
+            lox.parse("1 + 2 * x").unwrap()
+        );
+        // positions don't take part in equality
+        assert_eq!(
+            lox.parse("1+2*x").unwrap(),
+            lox.parse("1 +\n 2 * x").unwrap()
+        );
+        assert_ne!(lox.parse("1 + 2").unwrap(), lox.parse("2 + 1").unwrap());
+        assert_ne!(lox.parse("(1 + 2)").unwrap(), lox.parse("1 + 2").unwrap());
+    }
+
+    #[test]
+    fn argument_count_is_capped_while_parsing() {
+        let lox = Lox::new();
+        let call = |count: usize| format!("max({})", vec!["1"; count].join(", "));
+        assert!(lox.parse(&call(MAX_ARGUMENTS)).is_ok());
+
+        let error = lox.parse(&call(MAX_ARGUMENTS + 1)).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.message, "Can't have more than 255 arguments.");
+    }
+
+    #[test]
+    fn every_operator_tier_parses_as_before() {
+        let lox = Lox::new();
+        let cases = [
+            ("(1, 2, 3)", "(group (, (, 1 2) 3))"),
+            ("a = b = 1", "(= a (= b 1))"),
+            ("a %= 2", "(= a (% a 2))"),
+            ("a ? b : c ? d : e", "(?: a b (?: c d e))"),
+            ("a = b ? 1 : 2", "(= a (?: b 1 2))"),
+            ("1 == 2 != 3", "(!= (== 1 2) 3)"),
+            ("1 < 2 == 3 >= 4", "(== (< 1 2) (>= 3 4))"),
+            ("1 > 2 != 3 <= 4", "(!= (> 1 2) (<= 3 4))"),
+            ("1 .. 2 + 3", "(.. 1 (+ 2 3))"),
+            ("1 - 2 + 3", "(+ (- 1 2) 3)"),
+            ("1 + 2 * 3 / 4 % 5", "(+ 1 (% (/ (* 2 3) 4) 5))"),
+            ("-!+~1", "(- (! (+ (~ 1))))"),
+            ("-a * b", "(* (- a) b)"),
+            ("typeof a == \"number\"", "(== (typeof a) \"number\")"),
+            ("f(1)(2)[3].x", "(. (index (call (call f 1) 2) 3) x)"),
+            ("-f(1)", "(- (call f 1))"),
+            ("(1 + 2) * 3", "(* (group (+ 1 2)) 3)"),
+            ("{1: 2}[1]", "(index (map 1 2) 1)"),
+        ];
+        for (source, lisp) in cases {
+            let expr = lox.parse(source).unwrap();
+            assert_eq!(expr.print(Notation::Lisp), lisp, "{}", source);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_a_clean_error() {
+        on_main_stack(|| {
+            let lox = Lox::new();
+            let deep = [
+                format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000)),
+                format!("{}1", "-".repeat(10_000)),
+                format!("1{}", " + 1".repeat(10_000)),
+            ];
+            let messages = [
+                "Maximum nesting depth exceeded",
+                "Maximum nesting depth exceeded",
+                "Too many chained operators",
+            ];
+            for (source, message) in deep.iter().zip(messages) {
+                let error = lox.parse(source).unwrap_err();
+                assert_eq!(error.kind, ErrorKind::Parse);
+                assert_eq!(error.message, message);
+            }
+            // just under the limit parses and evaluates
+            let nested = MAX_DEPTH - 2;
+            let shallow = format!("{}1{}", "(".repeat(nested), ")".repeat(nested));
+            assert!(lox.parse(&shallow).is_ok());
+            assert_eq!(run(&format!("print {};", shallow)).unwrap(), "1\n");
+        });
+    }
+
+    #[test]
+    fn long_operator_chains_parse() {
+        on_main_stack(|| {
+            let lox = Lox::new();
+            let chain = format!("1{}", " + 1".repeat(MAX_CHAIN));
+            assert!(lox.parse(&chain).is_ok());
+            let sum = format!("{}\n", MAX_CHAIN + 1);
+            assert_eq!(run(&format!("print {};", chain)).unwrap(), sum);
+
+            // the operators of a chain don't count towards how deep it nests
+            let nested = MAX_DEPTH - 2;
+            let grouped = format!("{}{}{}", "(".repeat(nested), chain, ")".repeat(nested));
+            assert!(lox.parse(&grouped).is_ok());
+
+            let error = lox.parse(&format!("{} + 1", chain)).unwrap_err();
+            assert_eq!(error.message, "Too many chained operators");
+        });
+    }
+
+    /// run `test` on a thread with the 8 MiB stack the interpreter gets as the main thread,
+    /// rather than the 2 MiB of a test thread the limits aren't sized for
+    fn on_main_stack(test: impl FnOnce() + Send + 'static) {
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn one_trailing_comma_is_allowed() {
+        let lox = Lox::new();
+        assert_eq!(
+            lox.parse("max(1, 2,)").unwrap(),
+            lox.parse("max(1, 2)").unwrap()
+        );
+        assert_eq!(lox.parse("{1: 2,}").unwrap(), lox.parse("{1: 2}").unwrap());
+        assert_eq!(lox.parse("(1, 2,)").unwrap(), lox.parse("(1, 2)").unwrap());
+        assert_eq!(run("print max(1, 2,);").unwrap(), "2\n");
+        assert_eq!(run("print (1, 2,);").unwrap(), "2\n");
+
+        for source in ["max(1,,)", "max(,)", "{1: 2,,}", "(1,,)", "(,)"] {
+            assert_eq!(lox.parse(source).unwrap_err().kind, ErrorKind::Parse);
+        }
+    }
+
+    #[test]
+    fn empty_input_parses() {
+        let lox = Lox::new();
+        for source in ["", "  \n\t"] {
+            assert_eq!(lox.parse(source).unwrap().print(Notation::Lisp), "nil");
+            assert!(lox.parse_program(source).unwrap().is_empty());
+            assert_eq!(run(source).unwrap(), "");
+        }
+    }
+
+    #[test]
+    fn chained_comparisons_are_rejected_with_a_hint() {
+        let lox = Lox::new();
+        let error = lox.parse("1 < 2 < 3").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.message, "Comparison operators can't be chained");
+        assert_eq!(error.position.column, 7);
+        assert!(error.help.unwrap().contains("a < b ? b < c : false"));
+
+        // explicit parentheses and other operators are left alone
+        assert!(lox.parse("(1 < 2) < 3").is_ok());
+        assert!(lox.parse("1 < 2 == 2 > 1").is_ok());
+    }
+
+    // comparisons are left out, `a < b < c` is rejected rather than parsed
+    const BINARY_OPERATORS: [(&str, u8); 7] = [
+        ("==", EQUALITY),
+        ("!=", EQUALITY),
+        ("+", TERM),
+        ("-", TERM),
+        ("*", FACTOR),
+        ("/", FACTOR),
+        ("%", FACTOR),
+    ];
+
+    /// a random expression for the round-trip property, without the tokens an `Expr` carries
+    #[derive(Debug, Clone)]
+    enum Tree {
+        Number(u8),
+        Unary(&'static str, Box<Tree>),
+        Binary(Box<Tree>, (&'static str, u8), Box<Tree>),
+    }
+
+    impl Tree {
+        fn random(rng: &mut Rng, depth: usize) -> Tree {
+            let roll = rng.next_f64();
+            if depth == 0 || roll < 0.3 {
+                return Tree::Number((rng.next_f64() * 10.0) as u8);
+            }
+            if roll < 0.45 {
+                let operator = if rng.next_f64() < 0.5 { "-" } else { "!" };
+                return Tree::Unary(operator, Box::new(Tree::random(rng, depth - 1)));
+            }
+            let index = (rng.next_f64() * BINARY_OPERATORS.len() as f64) as usize;
+            Tree::Binary(
+                Box::new(Tree::random(rng, depth - 1)),
+                BINARY_OPERATORS[index],
+                Box::new(Tree::random(rng, depth - 1)),
+            )
+        }
+
+        fn precedence(&self) -> u8 {
+            match self {
+                Tree::Number(_) => u8::MAX,
+                Tree::Unary(..) => UNARY,
+                Tree::Binary(_, (_, precedence), _) => *precedence,
+            }
+        }
+
+        /// infix source with only the parentheses the precedence rules call for, mirroring the
+        /// parser: a left operand needs `precedence`, a right one `precedence + 1`
+        fn source(&self, min: u8) -> String {
+            let text = match self {
+                Tree::Number(n) => n.to_string(),
+                Tree::Unary(operator, right) => format!("{} {}", operator, right.source(UNARY)),
+                Tree::Binary(left, (operator, precedence), right) => format!(
+                    "{} {} {}",
+                    left.source(*precedence),
+                    operator,
+                    right.source(precedence + 1)
+                ),
+            };
+            match self.precedence() < min {
+                true => format!("({})", text),
+                false => text,
+            }
+        }
+
+        /// infix source with every operator in its own parentheses
+        fn parenthesized(&self) -> String {
+            match self {
+                Tree::Number(n) => n.to_string(),
+                Tree::Unary(operator, right) => format!("({} {})", operator, right.parenthesized()),
+                Tree::Binary(left, (operator, _), right) => format!(
+                    "({} {} {})",
+                    left.parenthesized(),
+                    operator,
+                    right.parenthesized()
+                ),
+            }
+        }
+
+        fn lisp(&self) -> String {
+            match self {
+                Tree::Number(n) => n.to_string(),
+                Tree::Unary(operator, right) => format!("({} {})", operator, right.lisp()),
+                Tree::Binary(left, (operator, _), right) => {
+                    format!("({} {} {})", operator, left.lisp(), right.lisp())
+                }
+            }
+        }
+
+        /// trees one step smaller: a subtree, or the tree with one operand shrunk
+        fn smaller(&self) -> Vec<Tree> {
+            match self {
+                Tree::Number(0) => vec![],
+                Tree::Number(_) => vec![Tree::Number(0)],
+                Tree::Unary(operator, right) => {
+                    let mut trees = vec![(**right).clone()];
+                    for right in right.smaller() {
+                        trees.push(Tree::Unary(operator, Box::new(right)));
+                    }
+                    trees
+                }
+                Tree::Binary(left, operator, right) => {
+                    let mut trees = vec![(**left).clone(), (**right).clone()];
+                    for smaller in left.smaller() {
+                        trees.push(Tree::Binary(Box::new(smaller), *operator, right.clone()));
+                    }
+                    for smaller in right.smaller() {
+                        trees.push(Tree::Binary(left.clone(), *operator, Box::new(smaller)));
+                    }
+                    trees
+                }
+            }
+        }
+    }
+
+    /// the tree without the groupings the source parentheses left in it
+    fn ungroup(expr: Expr) -> Expr {
+        match expr {
+            Expr::Grouping { expr, .. } => ungroup(*expr),
+            Expr::Unary { operator, right } => Expr::Unary {
+                operator,
+                right: Box::new(ungroup(*right)),
+            },
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => Expr::Binary {
+                left: Box::new(ungroup(*left)),
+                operator,
+                right: Box::new(ungroup(*right)),
+            },
+            expr => expr,
+        }
+    }
+
+    /// why a tree doesn't survive being printed and parsed again, if it doesn't
+    fn round_trip_failure(lox: &Lox, tree: &Tree) -> Option<String> {
+        for source in [tree.source(0), tree.parenthesized()] {
+            let parsed = match lox.parse(&source) {
+                Ok(expr) => ungroup(expr).print(Notation::Lisp),
+                Err(error) => error.message,
+            };
+            if parsed != tree.lisp() {
+                return Some(format!("{} parsed as {}", source, parsed));
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn printed_expressions_parse_back_to_the_same_tree() {
+        let lox = Lox::new();
+        let mut rng = Rng::new(619);
+        for _ in 0..500 {
+            let mut tree = Tree::random(&mut rng, 6);
+            if round_trip_failure(&lox, &tree).is_none() {
+                continue;
+            }
+            // shrink to a minimal failing tree before reporting it
+            'shrink: loop {
+                for smaller in tree.smaller() {
+                    if round_trip_failure(&lox, &smaller).is_some() {
+                        tree = smaller;
+                        continue 'shrink;
+                    }
+                }
+                break;
+            }
+            panic!("{}", round_trip_failure(&lox, &tree).unwrap());
+        }
+    }
+
+    #[test]
+    fn this_and_super_parse_into_their_nodes() {
+        let lox = Lox::new();
+        let expr = lox.parse("this.x").unwrap();
+        assert!(matches!(&expr, Expr::Get { object, .. } if matches!(**object, Expr::This { .. })));
+        assert_eq!(expr.print(Notation::Lisp), "(. this x)");
+
+        let expr = lox.parse("super.init(1)").unwrap();
+        let Expr::Call { callee, .. } = &expr else {
+            panic!("expected a call, got {:?}", expr);
+        };
+        assert!(matches!(&**callee, Expr::Super { method, .. } if method.lexeme == "init"));
+
+        let error = lox.parse("super;").unwrap_err();
+        assert_eq!(error.message, "Expected '.' after 'super'.");
+        let error = lox.parse("super.1").unwrap_err();
+        assert_eq!(error.message, "Expected superclass method name.");
+
+        // they parse anywhere, it's running them outside a class that fails
+        let error = run("print this;").unwrap_err();
+        assert_eq!(error.message, "Can't use 'this' outside of a class");
+    }
+}
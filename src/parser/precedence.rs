@@ -0,0 +1,88 @@
+use crate::token::TokenKind;
+
+// binding power of each level of the grammar, higher binds tighter
+pub const COMMA: u8 = 1;
+pub const ASSIGNMENT: u8 = 2;
+pub const TERNARY: u8 = 3;
+pub const EQUALITY: u8 = 4;
+pub const COMPARISON: u8 = 5;
+pub const RANGE: u8 = 6;
+pub const TERM: u8 = 7;
+pub const FACTOR: u8 = 8;
+pub const UNARY: u8 = 9;
+pub const CALL: u8 = 10;
+
+/// how a chain of operators with the same precedence groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,  // a - b - c == (a - b) - c
+    Right, // a = b = c == a = (b = c)
+}
+
+/// precedence of a token used as an infix (or postfix, for calls and indexing) operator
+pub fn precedence(kind: TokenKind) -> Option<u8> {
+    match kind {
+        TokenKind::Comma => Some(COMMA),
+        TokenKind::Equal | TokenKind::PercentEqual => Some(ASSIGNMENT),
+        TokenKind::Question => Some(TERNARY),
+        TokenKind::BangEqual | TokenKind::EqualEqual => Some(EQUALITY),
+        TokenKind::Greater | TokenKind::GreaterEqual | TokenKind::Less | TokenKind::LessEqual => {
+            Some(COMPARISON)
+        }
+        TokenKind::DotDot => Some(RANGE),
+        TokenKind::Plus | TokenKind::Minus => Some(TERM),
+        TokenKind::Slash | TokenKind::Star | TokenKind::Percent => Some(FACTOR),
+        TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::Dot => Some(CALL),
+        _ => None,
+    }
+}
+
+/// precedence of a token used as a prefix operator
+pub fn prefix_precedence(kind: TokenKind) -> Option<u8> {
+    match kind {
+        TokenKind::Bang
+        | TokenKind::Minus
+        | TokenKind::Plus
+        | TokenKind::Tilde
+        | TokenKind::TypeOf => Some(UNARY),
+        _ => None,
+    }
+}
+
+/// associativity of an infix operator, `None` for tokens that are not one
+pub fn associativity(kind: TokenKind) -> Option<Associativity> {
+    match kind {
+        TokenKind::Equal | TokenKind::PercentEqual | TokenKind::Question => {
+            Some(Associativity::Right)
+        }
+        _ => precedence(kind).map(|_| Associativity::Left),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_binds_tighter_than_plus() {
+        assert!(precedence(TokenKind::Star) > precedence(TokenKind::Plus));
+    }
+
+    #[test]
+    fn equality_binds_looser_than_comparison() {
+        assert!(precedence(TokenKind::EqualEqual) < precedence(TokenKind::Less));
+    }
+
+    #[test]
+    fn only_assignment_and_ternary_group_to_the_right() {
+        assert_eq!(associativity(TokenKind::Equal), Some(Associativity::Right));
+        assert_eq!(
+            associativity(TokenKind::Question),
+            Some(Associativity::Right)
+        );
+        assert_eq!(associativity(TokenKind::Minus), Some(Associativity::Left));
+        assert_eq!(associativity(TokenKind::Semicolon), None);
+        assert_eq!(prefix_precedence(TokenKind::Minus), Some(UNARY));
+        assert_eq!(prefix_precedence(TokenKind::Star), None);
+    }
+}
@@ -1,18 +1,110 @@
 use crate::error::{Error, ErrorKind, Position};
+use crate::interpreter::natives::NativeFunction;
+use crate::parser::map::Map;
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
 
 /// separation of concerns
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
-    String(String),
+    String(Rc<str>), // shared, cloning a string value doesn't copy the text
+    Bytes(Vec<u8>),
     Bool(bool),
     Nil,
+    Native(NativeFunction),
+    Map(Map),        // iterates in insertion order, see `Map`
+    Range(i64, i64), // start..end, the end is excluded and a start past the end is empty
 }
+
+/// a value that can key a map: numbers, strings, booleans and nil.
+/// numbers are stored by bit pattern so they can be hashed, `to_key` rejects NaN
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Number(u64), // f64::to_bits, with -0.0 folded into 0.0 since they compare equal
+    String(Rc<str>),
+    Bool(bool),
+    Nil,
+}
+
+impl Key {
+    pub fn to_value(&self) -> Value {
+        match self {
+            Key::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            Key::String(s) => Value::String(s.clone()),
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Nil => Value::Nil,
+        }
+    }
+}
+
+/// equality between values of different types is always false.
+/// numbers follow IEEE 754, so `NaN` is never equal to anything, not even itself
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Native(a), Value::Native(b)) => a.name == b.name,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::Range(a, b), Value::Range(c, d)) => (a, b) == (c, d),
+            _ => false,
+        }
+    }
+}
+/// how a number is written everywhere it's shown: without a trailing ".0", and with NaN and the
+/// infinities spelled `nan`, `inf` and `-inf`
+pub fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        "nan".to_string()
+    } else {
+        n.to_string() // `inf` and `-inf` already
+    }
+}
+
+/// how values are shown to the user, numbers drop a trailing ".0"
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", format_number(*n)),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Bytes(bytes) => {
+                // written back as a literal, bytes that aren't printable ascii as \xHH
+                write!(f, "b\"")?;
+                for &byte in bytes {
+                    match byte {
+                        b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+                        0x20..=0x7e => write!(f, "{}", byte as char)?,
+                        _ => write!(f, "\\x{:02x}", byte)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Nil => write!(f, "nil"),
+            Value::Native(native) => write!(f, "<native fn {}>", native.name),
+            Value::Map(map) => {
+                let entries: Vec<String> = map
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key.to_value().repr(), value.repr()))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Value::Range(start, end) => write!(f, "{}..{}", start, end),
+        }
+    }
+}
+
 impl Value {
-    /// function to evaluate boolean values
+    /// lox truthiness: `false` and `nil` are false, every other value (`0` and `""` too) is true
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::Bool(b) => *b,
+            Value::Nil => false,
             _ => true,
         }
     }
@@ -21,25 +113,327 @@ impl Value {
         match self {
             Value::Number(_) => "number",
             Value::String(_) => "string",
+            Value::Bytes(_) => "bytes",
             Value::Bool(_) => "boolean",
             Value::Nil => "nil",
+            Value::Native(_) => "native function",
+            Value::Map(_) => "map",
+            Value::Range(..) => "range",
+        }
+    }
+
+    /// how many numbers `start..end` goes through
+    pub fn range_len(start: i64, end: i64) -> u64 {
+        end.saturating_sub(start).max(0) as u64
+    }
+
+    /// the numbers of a range in order, for anything that loops over one
+    pub fn range_values(start: i64, end: i64) -> impl Iterator<Item = Value> {
+        (start..end).map(|n| Value::Number(n as f64))
+    }
+
+    /// a copy with every number rounded to `digits` significant digits, used to display values
+    /// at a fixed precision. map keys are left alone so distinct keys stay distinct
+    pub fn round_to(&self, digits: usize) -> Value {
+        match self {
+            Value::Number(n) if n.is_finite() && *n != 0.0 => {
+                // `{:e}` rounds the mantissa, parsing it back drops the digits that were cut
+                let rounded = format!("{:.*e}", digits.max(1) - 1, n);
+                Value::Number(rounded.parse().unwrap_or(*n))
+            }
+            Value::Map(map) => Value::Map(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.round_to(digits)))
+                    .collect(),
+            ),
+            _ => self.clone(),
         }
     }
 
-    /// function to make arithmetic operations only if values are numbers
-    pub fn binary_number_operation<F>(left: &Value, right: &Value, op: F, position: Position) -> Result<Value, Error>
+    /// how the value reads inside a map, strings are quoted so `{"1": 1}` and `{1: 1}` differ
+    pub fn repr(&self) -> String {
+        match self {
+            Value::String(s) => format!("{:?}", s),
+            _ => self.to_string(),
+        }
+    }
+
+    /// the map key for this value, NaN and functions can't be keys
+    pub fn to_key(&self, position: Position) -> Result<Key, Error> {
+        match self {
+            Value::Number(n) if n.is_nan() => {
+                Err(Error::runtime("NaN can't be used as a map key", position))
+            }
+            Value::Number(n) if *n == 0.0 => Ok(Key::Number(0f64.to_bits())),
+            Value::Number(n) => Ok(Key::Number(n.to_bits())),
+            Value::String(s) => Ok(Key::String(s.clone())),
+            Value::Bool(b) => Ok(Key::Bool(*b)),
+            Value::Nil => Ok(Key::Nil),
+            Value::Bytes(_) | Value::Native(_) | Value::Map(_) | Value::Range(..) => {
+                Err(Error::type_error(
+                    format!(
+                        "A value of type {} can't be used as a map key",
+                        self.type_name()
+                    ),
+                    position,
+                )
+                .with_help("Map keys must be numbers, strings, booleans or nil"))
+            }
+        }
+    }
+
+    /// function to compare two numbers or two strings (lexicographically), `test` receives
+    /// the ordering which is `None` when a NaN is involved so every comparison is false
+    pub fn compare<F>(
+        left: &Value,
+        right: &Value,
+        op: &str,
+        test: F,
+        position: Position,
+    ) -> Result<Value, Error>
+    where
+        F: FnOnce(Option<Ordering>) -> bool,
+    {
+        let ordering = match (left, right) {
+            (Value::Number(l), Value::Number(r)) => l.partial_cmp(r),
+            (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+            _ => {
+                return Err(Error::type_error(
+                    format!(
+                        "Invalid operand types for {}: {} and {}",
+                        op,
+                        left.type_name(),
+                        right.type_name()
+                    ),
+                    position,
+                )
+                .with_help(format!(
+                    "The {} operator compares two numbers or two strings",
+                    op
+                )))
+            }
+        };
+        Ok(Value::Bool(test(ordering)))
+    }
+
+    /// function to make arithmetic operations only if values are numbers, `op` is the operator
+    /// as written, named in the error along with both operand types (left first)
+    pub fn binary_number_operation<F>(
+        left: &Value,
+        right: &Value,
+        op: &str,
+        operation: F,
+        position: Position,
+    ) -> Result<Value, Error>
     where
         F: FnOnce(f64, f64) -> f64,
     {
         if let (Value::Number(l), Value::Number(r)) = (left, right) {
-            Ok(Value::Number(op(*l, *r)))
+            Ok(Value::Number(operation(*l, *r)))
         } else {
-            Err(Error::invalid_operand_types(
-                "Operands must be numbers.",
-                left.type_name(),
-                right.type_name(),
-                position,
-            ))
+            Err(
+                Error::invalid_operand_types(op, left.type_name(), right.type_name(), position)
+                    .with_help(format!("The {} operator only applies to numbers", op)),
+            )
+        }
+    }
+}
+
+/// conversions for code embedding the interpreter, e.g. to pass arguments to a native
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.into())
+    }
+}
+
+/// the type error of a failed conversion, it isn't tied to any source so it points at the start
+fn conversion_error(expected: &str, value: &Value) -> Error {
+    Error::type_error(
+        format!("Expected {}, found {}", expected, value.type_name()),
+        Position::new(1, 1, 0),
+    )
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Number(n) => Ok(n),
+            _ => Err(conversion_error("a number", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            _ => Err(conversion_error("a boolean", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::String(s) => Ok(s.to_string()),
+            _ => Err(conversion_error("a string", &value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::interpreter::runtime::test_support;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn key(value: Value) -> Result<Key, Error> {
+        value.to_key(Position::new(1, 1, 0))
+    }
+
+    fn hash(key: &Key) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn nan_is_not_equal_to_itself() {
+        let nan = Value::Number(f64::NAN);
+        assert_ne!(nan, nan.clone());
+        assert_eq!(Value::Number(1.0), Value::Number(1.0));
+    }
+
+    #[test]
+    fn nan_from_zero_over_zero_is_not_equal_to_itself() {
+        let (mut lox, output) = test_support::lox();
+        lox.interpreter.ieee_numbers = true;
+        lox.run("var x = 0 / 0; print x == x; print x != x;")
+            .unwrap();
+        assert_eq!(output.contents(), "false\ntrue\n");
+    }
+
+    #[test]
+    fn non_finite_numbers_print_as_nan_and_inf() {
+        let (mut lox, output) = test_support::lox();
+        lox.interpreter.ieee_numbers = true;
+        lox.run("print 0 / 0, -(0 / 0); print 1e200 * 1e200, -1e200 * 1e200;")
+            .unwrap();
+        assert_eq!(output.contents(), "nan nan\ninf -inf\n");
+        // the same spellings inside maps and in string conversion
+        lox.run("print {\"x\": 0 / 0};").unwrap();
+        assert_eq!(Value::Number(f64::NEG_INFINITY).to_string(), "-inf");
+        assert!(output.contents().ends_with("{\"x\": nan}\n"));
+    }
+
+    #[test]
+    fn copied_string_values_share_their_text() {
+        let (mut lox, _) = test_support::lox();
+        let text: Rc<str> = "x".repeat(100_000).into();
+        let value = Value::String(text.clone());
+        lox.interpreter.globals.define("s", value);
+        lox.run("var t = s; var u = {\"k\": t};").unwrap();
+        assert_eq!(Rc::strong_count(&text), 4); // ours, s, t and the one in the map
+    }
+
+    #[test]
+    fn values_of_different_types_are_not_equal() {
+        assert_eq!(test_support::run("1 == \"1\"").unwrap(), "false\n");
+        assert_eq!(test_support::run("nil == false").unwrap(), "false\n");
+        assert_eq!(test_support::run("nil == nil").unwrap(), "true\n");
+    }
+
+    #[test]
+    fn equal_values_make_equal_keys() {
+        let pairs = [
+            (Value::Number(1.5), Value::Number(1.5)),
+            (Value::Number(0.0), Value::Number(-0.0)),
+            (Value::String("a".into()), Value::String("a".into())),
+            (Value::Nil, Value::Nil),
+        ];
+        for (a, b) in pairs {
+            let (a, b) = (key(a).unwrap(), key(b).unwrap());
+            assert_eq!(a, b);
+            assert_eq!(hash(&a), hash(&b));
         }
+        let (number, string) = (Value::Number(1.0), Value::String("1".into()));
+        assert_ne!(key(number).unwrap(), key(string).unwrap());
+    }
+
+    #[test]
+    fn nan_and_collections_are_not_keys() {
+        let error = key(Value::Number(f64::NAN)).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(error.message, "NaN can't be used as a map key");
+
+        let error = key(Value::Map(Default::default())).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(
+            error.message,
+            "A value of type map can't be used as a map key"
+        );
+    }
+
+    #[test]
+    fn byte_strings_have_a_length_and_byte_indexes() {
+        let output =
+            test_support::run("var b = b\"a\\x41\\xff\"; print len(b), b[0], b[1], b[2], b;");
+        assert_eq!(output.unwrap(), "3 97 65 255 b\"aA\\xff\"\n");
+
+        let error = test_support::run("b\"ab\"[2];").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Runtime);
+        assert_eq!(
+            test_support::run("b\"\\xZZ\";").unwrap_err().kind,
+            ErrorKind::Syntax
+        );
+    }
+
+    #[test]
+    fn rust_values_convert_to_lox_values() {
+        assert_eq!(Value::from(1.5), Value::Number(1.5));
+        assert_eq!(Value::from(true), Value::Bool(true));
+        assert_eq!(Value::from(String::from("a")), Value::String("a".into()));
+        assert_eq!(Value::from("a"), Value::String("a".into()));
+    }
+
+    #[test]
+    fn lox_values_convert_back_to_matching_rust_types() {
+        assert_eq!(f64::try_from(Value::Number(2.0)).unwrap(), 2.0);
+        assert!(bool::try_from(Value::Bool(false)).is_ok_and(|b| !b));
+        assert_eq!(String::try_from(Value::from("hi")).unwrap(), "hi");
+
+        let error = f64::try_from(Value::from("1")).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(error.message, "Expected a number, found string");
+        let error = bool::try_from(Value::Nil).unwrap_err();
+        assert_eq!(error.message, "Expected a boolean, found nil");
+        let error = String::try_from(Value::Number(1.0)).unwrap_err();
+        assert_eq!(error.message, "Expected a string, found number");
     }
 }
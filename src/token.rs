@@ -1,10 +1,15 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TokenKind {
     // single-character tokens
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -12,9 +17,12 @@ pub enum TokenKind {
     Semicolon,
     Slash,
     Star,
+    Percent,
     Colon,
     Question,
+    Tilde,
     // one-two character tokens
+    DotDot, // `a..b`, a range
     Bang,
     BangEqual,
     Equal,
@@ -23,9 +31,11 @@ pub enum TokenKind {
     GreaterEqual,
     Less,
     LessEqual,
+    PercentEqual,
     // literals
     Identifier,
     String,
+    Bytes, // b"...", the lexeme keeps the escapes as written
     Number,
     // Keywords
     And,
@@ -44,8 +54,81 @@ pub enum TokenKind {
     True,
     Var,
     While,
+    Switch,
+    Case,
+    Default,
+    Const,
+    In,
+    TypeOf,
+    // trivia, only emitted when the scanner keeps comments
+    Comment,
     Eof,
 }
+
+/// friendly names used by error messages, `Debug` is kept for the raw variant name
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TokenKind::LeftParen => "'('",
+            TokenKind::RightParen => "')'",
+            TokenKind::LeftBrace => "'{'",
+            TokenKind::RightBrace => "'}'",
+            TokenKind::LeftBracket => "'['",
+            TokenKind::RightBracket => "']'",
+            TokenKind::Comma => "','",
+            TokenKind::Dot => "'.'",
+            TokenKind::Minus => "'-'",
+            TokenKind::Plus => "'+'",
+            TokenKind::Semicolon => "';'",
+            TokenKind::Slash => "'/'",
+            TokenKind::Star => "'*'",
+            TokenKind::Percent => "'%'",
+            TokenKind::Colon => "':'",
+            TokenKind::Question => "'?'",
+            TokenKind::Tilde => "'~'",
+            TokenKind::DotDot => "'..'",
+            TokenKind::Bang => "'!'",
+            TokenKind::BangEqual => "'!='",
+            TokenKind::Equal => "'='",
+            TokenKind::EqualEqual => "'=='",
+            TokenKind::Greater => "'>'",
+            TokenKind::GreaterEqual => "'>='",
+            TokenKind::Less => "'<'",
+            TokenKind::LessEqual => "'<='",
+            TokenKind::PercentEqual => "'%='",
+            TokenKind::Identifier => "identifier",
+            TokenKind::String => "string",
+            TokenKind::Bytes => "byte string",
+            TokenKind::Number => "number",
+            TokenKind::And => "'and'",
+            TokenKind::Class => "'class'",
+            TokenKind::Else => "'else'",
+            TokenKind::False => "'false'",
+            TokenKind::Fun => "'fun'",
+            TokenKind::For => "'for'",
+            TokenKind::If => "'if'",
+            TokenKind::Print => "'print'",
+            TokenKind::Return => "'return'",
+            TokenKind::Or => "'or'",
+            TokenKind::Nil => "'nil'",
+            TokenKind::Super => "'super'",
+            TokenKind::This => "'this'",
+            TokenKind::True => "'true'",
+            TokenKind::Var => "'var'",
+            TokenKind::While => "'while'",
+            TokenKind::Switch => "'switch'",
+            TokenKind::Case => "'case'",
+            TokenKind::Default => "'default'",
+            TokenKind::Const => "'const'",
+            TokenKind::In => "'in'",
+            TokenKind::TypeOf => "'typeof'",
+            TokenKind::Comment => "comment",
+            TokenKind::Eof => "end of input",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     // size = 24 bytes (usize) + 1 byte (enum) +  variable size string
@@ -53,7 +136,7 @@ pub struct Token {
     pub kind: TokenKind,     // type of the token
     pub line: usize,         // where token appears
     pub column: usize,       // column where token starts
-    pub length: usize,       // size of the lexeme
+    pub length: usize,       // size of the lexeme in the source, quotes of a string included
     pub offset: usize,
 }
 
@@ -71,6 +154,65 @@ impl Token {
     }
 }
 
+/// byte range `start..end` of some code in the source it was scanned from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// the smallest span covering both
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+    /// the text this span covers, empty if it doesn't fit in the source
+    pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+        source.get(self.start..self.end).unwrap_or("")
+    }
+}
+
+impl Token {
+    /// where the token was in the source, quotes of a string included
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.offset,
+            end: self.offset + self.length,
+        }
+    }
+
+    /// one line like `String[1:7] "\"hi\""` with the text exactly as written in `source`,
+    /// escaped so it stays on one line. stable enough to compare scanner output against
+    pub fn to_debug_line(&self, source: &str) -> String {
+        format!(
+            "{:?}[{}:{}] {:?}",
+            self.kind,
+            self.line,
+            self.column,
+            self.span().slice(source)
+        )
+    }
+}
+
+/// tokens are equal when kind and lexeme match, the position is ignored so
+/// the same code parsed from differently spaced sources compares equal
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.lexeme == other.lexeme
+    }
+}
+
+/// hashes what equality compares, kind and lexeme
+impl Hash for Token {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.lexeme.hash(state);
+    }
+}
+
 // #[derive(Debug, Clone)] # experimentald DOD
 // pub struct SlimToken {
 //     // 16 bytes (usize) + 1 byte (enum)
@@ -91,3 +233,27 @@ impl Token {
 //         &source[self.offset..self.offset + self.length]
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::lox::Lox;
+
+    #[test]
+    fn token_kinds_display_as_readable_names() {
+        assert_eq!(TokenKind::Plus.to_string(), "'+'");
+        assert_eq!(TokenKind::RightParen.to_string(), "')'");
+        assert_eq!(TokenKind::Identifier.to_string(), "identifier");
+        assert_eq!(TokenKind::Eof.to_string(), "end of input");
+    }
+
+    #[test]
+    fn debug_line_shows_a_string_as_written() {
+        let source = "print \"hi\\n\";";
+        let tokens = Lox::new().tokenize(source).unwrap();
+        assert_eq!(
+            tokens[1].to_debug_line(source),
+            "String[1:7] \"\\\"hi\\\\n\\\"\""
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+use std::cell::{Cell, OnceCell};
+
+/// turns byte offsets back into the 1-based `line:column` positions the scanner reports, so code
+/// that only kept an offset (or a `Span`) can still point at the source
+pub struct LineIndex<'a> {
+    source: Cow<'a, str>,
+    line_starts: OnceCell<Vec<usize>>, // offset where each line begins, found on the first lookup
+    last: Cell<(usize, usize, usize)>, // offset, line and column of the previous lookup
+    tab_width: usize,                  // same meaning as in the scanner
+}
+
+impl<'a> LineIndex<'a> {
+    /// index a borrowed source, or an owned one for a holder such as the scanner
+    pub fn new(source: impl Into<Cow<'a, str>>) -> Self {
+        LineIndex {
+            source: source.into(),
+            line_starts: OnceCell::new(),
+            last: Cell::new((0, 1, 1)),
+            tab_width: 4,
+        }
+    }
+
+    /// columns after a tab, should match the width the source was scanned with
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.tab_width = width.max(1);
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// line and column of the char at `offset`, counting chars (not bytes) like the scanner.
+    /// an offset inside a char is the position of that char, one past the end is the end.
+    /// a leading BOM takes no column
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line_starts = self.line_starts.get_or_init(|| {
+            let mut starts = vec![bom_length(&self.source)];
+            starts.extend(self.source.match_indices('\n').map(|(i, _)| i + 1));
+            starts
+        });
+        let line = line_starts.partition_point(|&start| start <= offset).max(1);
+        let line_start = line_starts[line - 1];
+
+        // lookups in increasing order, as the scanner makes them, go on from the previous one
+        // so a long line isn't counted again for every token on it
+        let (last_offset, last_line, last_column) = self.last.get();
+        let (from, mut column) =
+            match last_line == line && (line_start..=offset).contains(&last_offset) {
+                true => (last_offset, last_column),
+                false => (line_start, 1),
+            };
+        for c in self.source[from..offset.max(from)].chars() {
+            column = match c {
+                '\t' => (column - 1) / self.tab_width * self.tab_width + self.tab_width + 1,
+                _ => column + 1,
+            };
+        }
+        self.last.set((offset, line, column));
+        (line, column)
+    }
+}
+
+/// bytes taken by a leading UTF-8 BOM, 0 when there is none
+pub fn bom_length(source: &str) -> usize {
+    match source.starts_with('\u{FEFF}') {
+        true => '\u{FEFF}'.len_utf8(),
+        false => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_map_to_lines_and_columns() {
+        let index = LineIndex::new("var x;\nprint x;\n");
+        assert_eq!(index.position(0), (1, 1));
+        assert_eq!(index.position(4), (1, 5));
+        assert_eq!(index.position(6), (1, 7)); // the newline ends its own line
+        assert_eq!(index.position(7), (2, 1));
+        assert_eq!(index.position(13), (2, 7));
+        assert_eq!(index.position(100), (3, 1));
+        // going back to an earlier offset doesn't reuse the later lookup
+        assert_eq!(index.position(2), (1, 3));
+    }
+
+    #[test]
+    fn columns_count_chars_not_bytes() {
+        let source = "\"é€😀\" + x";
+        let index = LineIndex::new(source);
+        let plus = source.find('+').unwrap();
+        assert_eq!(plus, 12);
+        assert_eq!(index.position(plus), (1, 7));
+        // inside a multi-byte char is that char
+        assert_eq!(index.position(3), (1, 3));
+        assert_eq!(index.position(1), (1, 2));
+    }
+
+    #[test]
+    fn tabs_and_a_leading_bom_match_the_scanner() {
+        let index = LineIndex::new("\tx\n  \ty").tab_width(4);
+        assert_eq!(index.position(1), (1, 5));
+        assert_eq!(index.position(6), (2, 5));
+
+        let index = LineIndex::new(String::from("\u{FEFF}x\ny"));
+        assert_eq!(index.position(3), (1, 1));
+        assert_eq!(index.position(5), (2, 1));
+    }
+}